@@ -0,0 +1,169 @@
+use super::*;
+use serde::Deserialize;
+
+/// What a `BouncingBullet` does at the edges once it is out of bounces.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum AfterBounces {
+    /// Flies out of the arena for good.
+    Despawn,
+    /// Comes back in on the opposite side, if the arena wraps at all.
+    Wrap,
+}
+
+impl AfterBounces {
+    pub fn write_state(&self, w: &mut StateWriter) {
+        w.write_u8(*self as u8);
+    }
+
+    pub fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(match r.read_u8()? {
+            0 => AfterBounces::Despawn,
+            1 => AfterBounces::Wrap,
+            v => return Err(state::invalid(format!("invalid after bounces {}", v))),
+        })
+    }
+}
+
+/// Reflects off the edges of the arena, whatever they do to other enemies,
+/// until it has bounced `bounces` times.
+pub struct BouncingBullet {
+    pos: Point2<f32>,
+    dim: Vector2<f32>,
+    vel: Vector2<f32>,
+    color: Color,
+    /// Bounces left before it goes by `then`.
+    bounces: u32,
+    then: AfterBounces,
+}
+
+impl BouncingBullet {
+    pub fn new(
+        pos: Point2<f32>,
+        dim: Vector2<f32>,
+        vel: Vector2<f32>,
+        color: Color,
+        bounces: u32,
+        then: AfterBounces,
+    ) -> Self {
+        Self {pos,dim,vel,color,bounces,then}
+    }
+}
+
+impl LoadActor for BouncingBullet {
+    const TYPE_NAME: &'static str = "bouncing_bullet";
+
+    fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(Self {
+            pos: r.read_finite_point()?,
+            dim: r.read_finite_vector()?,
+            vel: r.read_finite_vector()?,
+            color: r.read_color()?,
+            bounces: r.read_u32()?,
+            then: AfterBounces::read_state(r)?,
+        })
+    }
+}
+
+impl Actor for BouncingBullet {
+    fn type_name(&self) -> &'static str {
+        Self::TYPE_NAME
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["hazard", "bullet", "bouncing"]
+    }
+
+    #[inline]
+    fn get_pos(&self) -> Point2<f32> {
+        self.pos
+    }
+
+    #[inline]
+    fn get_rect(&self) -> Rect {
+        Rect{
+            x: self.pos.x - self.dim.x / 2.0,
+            y: self.pos.y - self.dim.y / 2.0,
+            w: self.dim.x,
+            h: self.dim.y,
+        }
+    }
+
+    #[inline]
+    fn get_vel(&self) -> Vector2<f32> {
+        self.vel
+    }
+
+    fn boundary(&self, _: &Arena) -> Boundary {
+        match (self.bounces, self.then) {
+            (0, AfterBounces::Despawn) => Boundary::Despawn,
+            (0, AfterBounces::Wrap) => Boundary::Wrap,
+            _ => Boundary::Bounce,
+        }
+    }
+
+    fn on_bounce(&mut self) {
+        self.bounces = self.bounces.saturating_sub(1);
+    }
+
+    #[inline]
+    fn set_pos(&mut self, pos: Point2<f32>) {
+        self.pos = pos
+    }
+
+    #[inline]
+    fn set_dim(&mut self, dim: Vector2<f32>) {
+        self.dim = dim
+    }
+
+    #[inline]
+    fn set_vel(&mut self, vel: Vector2<f32>) {
+        self.vel = vel
+    }
+
+    fn write_state(&self, w: &mut StateWriter) {
+        w.write_point(self.pos);
+        w.write_vector(self.dim);
+        w.write_vector(self.vel);
+        w.write_color(self.color);
+        w.write_u32(self.bounces);
+        self.then.write_state(w);
+    }
+
+    fn draw(
+        &self,
+        ctx: &mut ggez::Context,
+        mesh_builder: Option<&mut MeshBuilder>
+    ) -> ggez::GameResult
+    {
+        // Outlined while it still has bounces left, filled once it has none.
+        let mode = if self.bounces > 0 {
+            DrawMode::stroke(2.0)
+        }
+        else {
+            DrawMode::fill()
+        };
+        let rect = self.get_rect();
+        if let Some(mesh_builder) = mesh_builder {
+            mesh_builder.rectangle(
+                mode,
+                rect,
+                self.color,
+            );
+        }
+        else {
+            let mesh = Mesh::new_rectangle(
+                ctx,
+                mode,
+                rect,
+                self.color,
+            )?;
+            ggez::graphics::draw(
+                ctx,
+                &mesh,
+                DrawParam::default(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -4,19 +4,42 @@ pub struct Bullet {
     pos: Point2<f32>,
     dim: Vector2<f32>,
     vel: Vector2<f32>,
+    color: Color,
 }
 
 impl Bullet {
     pub fn new(
         pos: Point2<f32>, 
         dim: Vector2<f32>, 
-        vel: Vector2<f32>
+        vel: Vector2<f32>,
+        color: Color,
     ) -> Self {
-        Self {pos,dim,vel}
+        Self {pos,dim,vel,color}
+    }
+}
+
+impl LoadActor for Bullet {
+    const TYPE_NAME: &'static str = "bullet";
+
+    fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(Self {
+            pos: r.read_finite_point()?,
+            dim: r.read_finite_vector()?,
+            vel: r.read_finite_vector()?,
+            color: r.read_color()?,
+        })
     }
 }
 
 impl Actor for Bullet {
+    fn type_name(&self) -> &'static str {
+        Self::TYPE_NAME
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["hazard", "bullet"]
+    }
+
     #[inline]
     fn get_pos(&self) -> Point2<f32> {
         self.pos
@@ -52,6 +75,13 @@ impl Actor for Bullet {
         self.vel = vel
     }
 
+    fn write_state(&self, w: &mut StateWriter) {
+        w.write_point(self.pos);
+        w.write_vector(self.dim);
+        w.write_vector(self.vel);
+        w.write_color(self.color);
+    }
+
     fn draw(
         &self,
         ctx: &mut ggez::Context,
@@ -63,7 +93,7 @@ impl Actor for Bullet {
             mesh_builder.rectangle(
                 DrawMode::fill(),
                 rect,
-                [1.0, 0.0, 0.0, 1.0].into(),
+                self.color,
             );
         }
         else {
@@ -71,7 +101,7 @@ impl Actor for Bullet {
                 ctx,
                 DrawMode::fill(),
                 rect,
-                [1.0, 0.0, 0.0, 1.0].into(),
+                self.color,
             )?;
             ggez::graphics::draw(
                 ctx,
@@ -5,19 +5,47 @@ pub struct DrunkBullet {
     dim: Vector2<f32>,
     vel: Vector2<f32>,
     drunk: f32,
+    color: Color,
+    /// How far it sways, in pixels per second.
+    drunk_factor: f32,
 }
 
 impl DrunkBullet {
     pub fn new(
         pos: Point2<f32>, 
         dim: Vector2<f32>, 
-        vel: Vector2<f32>
+        vel: Vector2<f32>,
+        color: Color,
+        drunk_factor: f32,
     ) -> Self {
-        Self {pos,dim,vel, drunk: 0.0}
+        Self {pos,dim,vel, drunk: 0.0, color, drunk_factor}
+    }
+}
+
+impl LoadActor for DrunkBullet {
+    const TYPE_NAME: &'static str = "drunk_bullet";
+
+    fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(Self {
+            pos: r.read_finite_point()?,
+            dim: r.read_finite_vector()?,
+            vel: r.read_finite_vector()?,
+            drunk: r.read_f32()?,
+            color: r.read_color()?,
+            drunk_factor: r.read_f32()?,
+        })
     }
 }
 
 impl Actor for DrunkBullet {
+    fn type_name(&self) -> &'static str {
+        Self::TYPE_NAME
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["hazard", "bullet", "drunk"]
+    }
+
     #[inline]
     fn get_pos(&self) -> Point2<f32> {
         self.pos
@@ -53,14 +81,22 @@ impl Actor for DrunkBullet {
         self.vel = vel
     }
 
+    fn write_state(&self, w: &mut StateWriter) {
+        w.write_point(self.pos);
+        w.write_vector(self.dim);
+        w.write_vector(self.vel);
+        w.write_f32(self.drunk);
+        w.write_color(self.color);
+        w.write_f32(self.drunk_factor);
+    }
+
     fn update(&mut self, dt: f32) {
         const TAU: f32 = std::f32::consts::PI * 2.0;
-        const DRUNK_FACTOR: f32 = 80.0;
 
         self.drunk = na::wrap(self.drunk + dt, 0.0, TAU);
         self.add_pos(self.vel * dt);
         self.add_pos(
-            self.vel.normalize() * DRUNK_FACTOR * dt * self.drunk.cos()
+            self.vel.normalize() * self.drunk_factor * dt * self.drunk.cos()
         );
     }
 
@@ -75,7 +111,7 @@ impl Actor for DrunkBullet {
             mesh_builder.rectangle(
                 DrawMode::fill(),
                 rect,
-                [1.0, 0.5, 0.0, 1.0].into(),
+                self.color,
             );
         }
         else {
@@ -83,7 +119,7 @@ impl Actor for DrunkBullet {
                 ctx,
                 DrawMode::fill(),
                 rect,
-                [1.0, 0.5, 0.0, 1.0].into(),
+                self.color,
             )?;
             ggez::graphics::draw(
                 ctx,
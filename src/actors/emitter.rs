@@ -0,0 +1,303 @@
+use super::*;
+use rand::Rng;
+use serde::Deserialize;
+use std::f32::consts::TAU;
+
+use crate::archetype::Archetype;
+
+/// How an `Emitter` lays out each volley.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Pattern {
+    /// `count` bullets spread evenly all around.
+    Ring { count: u32 },
+    /// `arms` evenly spread bullets, turning by `angular_speed` radians per
+    /// second.
+    Spiral { arms: u32, angular_speed: f32 },
+    /// `count` bullets in a line at the player, each `speed_step` faster than
+    /// the one before.
+    Aimed { count: u32, speed_step: f32 },
+    /// `count` bullets fanned out over `arc` radians around the player, each
+    /// a little off at random.
+    Spread { count: u32, arc: f32 },
+}
+
+impl Pattern {
+    /// Bullets fired in each volley.
+    pub fn count(&self) -> u32 {
+        match *self {
+            Pattern::Ring { count }
+            | Pattern::Spiral { arms: count, .. }
+            | Pattern::Aimed { count, .. }
+            | Pattern::Spread { count, .. } => count,
+        }
+    }
+
+    pub fn write_state(&self, w: &mut StateWriter) {
+        match *self {
+            Pattern::Ring { count } => {
+                w.write_u8(0);
+                w.write_u32(count);
+            }
+            Pattern::Spiral { arms, angular_speed } => {
+                w.write_u8(1);
+                w.write_u32(arms);
+                w.write_f32(angular_speed);
+            }
+            Pattern::Aimed { count, speed_step } => {
+                w.write_u8(2);
+                w.write_u32(count);
+                w.write_f32(speed_step);
+            }
+            Pattern::Spread { count, arc } => {
+                w.write_u8(3);
+                w.write_u32(count);
+                w.write_f32(arc);
+            }
+        }
+    }
+
+    pub fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(match r.read_u8()? {
+            0 => Pattern::Ring { count: r.read_u32()? },
+            1 => Pattern::Spiral {
+                arms: r.read_u32()?,
+                angular_speed: r.read_f32()?,
+            },
+            2 => Pattern::Aimed {
+                count: r.read_u32()?,
+                speed_step: r.read_f32()?,
+            },
+            3 => Pattern::Spread {
+                count: r.read_u32()?,
+                arc: r.read_f32()?,
+            },
+            v => return Err(state::invalid(format!("invalid pattern {}", v))),
+        })
+    }
+}
+
+/// Sent from `Emitter::sense` to `Emitter::apply` after firing a volley.
+struct Fired;
+
+/// Fires a volley of bullets in a `Pattern` every `interval` seconds.
+pub struct Emitter {
+    pos: Point2<f32>,
+    dim: Vector2<f32>,
+    vel: Vector2<f32>,
+    color: Color,
+    pattern: Pattern,
+    interval: f32,
+    /// What it fires. Never an emitter or a laser.
+    shot: Archetype,
+    /// Seconds until the next volley.
+    cooldown: f32,
+    /// Direction of the first bullet of `Ring` and `Spiral` volleys.
+    angle: f32,
+}
+
+impl Emitter {
+    pub fn new(
+        pos: Point2<f32>,
+        dim: Vector2<f32>,
+        vel: Vector2<f32>,
+        color: Color,
+        pattern: Pattern,
+        interval: f32,
+        shot: Archetype,
+    ) -> Self {
+        Self {
+            pos,dim,vel,color,pattern,interval,shot,
+            cooldown: interval,
+            angle: 0.0,
+        }
+    }
+
+    /// Orbiters it fires circle the emitter itself, starting out in the
+    /// direction they were fired in.
+    fn volley(&self, id: ActorId, world: &World, rng: &mut GameRng) -> Vec<Command> {
+        let aim = world.player.get_pos() - self.pos;
+        let aim_angle = if aim.x == 0.0 && aim.y == 0.0 {
+            self.angle
+        }
+        else {
+            aim.y.atan2(aim.x)
+        };
+        let target = world.player.get_pos();
+        let (size, speed) = self.shot.roll(rng);
+        let fire = |angle: f32, speed: f32| if self.shot.is_orbiter() {
+            self.shot.orbiter(Anchor::Actor(id), self.pos, angle, 0.0, size, speed)
+        }
+        else {
+            self.shot.bullet(
+                self.pos,
+                size,
+                Vector2::new(angle.cos(), angle.sin()) * speed,
+                target,
+            )
+        };
+        match self.pattern {
+            Pattern::Ring { count: n } | Pattern::Spiral { arms: n, .. } => {
+                (0..n)
+                    .filter_map(|i| fire(self.angle + TAU * i as f32 / n as f32, speed))
+                    .map(Command::Spawn)
+                    .collect()
+            }
+            Pattern::Aimed { count, speed_step } => {
+                (0..count)
+                    .filter_map(|i| fire(aim_angle, speed + speed_step * i as f32))
+                    .map(Command::Spawn)
+                    .collect()
+            }
+            Pattern::Spread { count, arc } => {
+                let step = if count > 1 { arc / (count - 1) as f32 } else { 0.0 };
+                (0..count)
+                    .filter_map(|i| {
+                        let jitter = rng.gen_range(-0.25, 0.25) * step;
+                        fire(aim_angle - arc / 2.0 + step * i as f32 + jitter, speed)
+                    })
+                    .map(Command::Spawn)
+                    .collect()
+            }
+        }
+    }
+}
+
+impl LoadActor for Emitter {
+    const TYPE_NAME: &'static str = "emitter";
+
+    fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        let emitter = Self {
+            pos: r.read_finite_point()?,
+            dim: r.read_finite_vector()?,
+            vel: r.read_finite_vector()?,
+            color: r.read_color()?,
+            pattern: Pattern::read_state(r)?,
+            interval: r.read_f32()?,
+            shot: Archetype::read_state(r)?,
+            cooldown: r.read_f32()?,
+            angle: r.read_f32()?,
+        };
+        if !emitter.shot.can_be_fired() {
+            return Err(state::invalid(format!("emitter fires {}", emitter.shot.name)));
+        }
+        Ok(emitter)
+    }
+}
+
+impl Actor for Emitter {
+    fn type_name(&self) -> &'static str {
+        Self::TYPE_NAME
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["hazard", "emitter"]
+    }
+
+    #[inline]
+    fn get_pos(&self) -> Point2<f32> {
+        self.pos
+    }
+
+    #[inline]
+    fn get_rect(&self) -> Rect {
+        Rect{
+            x: self.pos.x - self.dim.x / 2.0,
+            y: self.pos.y - self.dim.y / 2.0,
+            w: self.dim.x,
+            h: self.dim.y,
+        }
+    }
+
+    #[inline]
+    fn get_vel(&self) -> Vector2<f32> {
+        self.vel
+    }
+
+    #[inline]
+    fn set_pos(&mut self, pos: Point2<f32>) {
+        self.pos = pos
+    }
+
+    #[inline]
+    fn set_dim(&mut self, dim: Vector2<f32>) {
+        self.dim = dim
+    }
+
+    #[inline]
+    fn set_vel(&mut self, vel: Vector2<f32>) {
+        self.vel = vel
+    }
+
+    fn write_state(&self, w: &mut StateWriter) {
+        w.write_point(self.pos);
+        w.write_vector(self.dim);
+        w.write_vector(self.vel);
+        w.write_color(self.color);
+        self.pattern.write_state(w);
+        w.write_f32(self.interval);
+        self.shot.write_state(w);
+        w.write_f32(self.cooldown);
+        w.write_f32(self.angle);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.cooldown -= dt;
+        if let Pattern::Spiral { angular_speed, .. } = self.pattern {
+            self.angle = (self.angle + angular_speed * dt).rem_euclid(TAU);
+        }
+        self.add_pos(self.vel * dt);
+    }
+
+    fn has_action(&self) -> bool { true }
+    fn sense(
+        &self,
+        id: ActorId,
+        world: &World,
+        rng: &mut GameRng,
+    ) -> Action {
+        if self.cooldown > 0.0 {
+            return Action::default();
+        }
+        Action {
+            intent: Some(Box::new(Fired)),
+            commands: self.volley(id, world, rng),
+        }
+    }
+
+    fn apply(&mut self, intent: Intent) {
+        if intent.is::<Fired>() {
+            self.cooldown += self.interval;
+        }
+    }
+
+    fn draw(
+        &self,
+        ctx: &mut ggez::Context,
+        mesh_builder: Option<&mut MeshBuilder>
+    ) -> ggez::GameResult
+    {
+        let rect = self.get_rect();
+        if let Some(mesh_builder) = mesh_builder {
+            mesh_builder.rectangle(
+                DrawMode::fill(),
+                rect,
+                self.color,
+            );
+        }
+        else {
+            let mesh = Mesh::new_rectangle(
+                ctx,
+                DrawMode::fill(),
+                rect,
+                self.color,
+            )?;
+            ggez::graphics::draw(
+                ctx,
+                &mesh,
+                DrawParam::default(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use super::*;
+
+/// Drifts slowly while pulling every bullet within `radius` towards itself,
+/// and the player too if `pulls_player` is set. A negative `strength` pushes
+/// them away instead.
+pub struct GravityWell {
+    pos: Point2<f32>,
+    dim: Vector2<f32>,
+    vel: Vector2<f32>,
+    color: Color,
+    radius: f32,
+    /// Pull at its center in pixels per second squared, falling off to
+    /// nothing at `radius`.
+    strength: f32,
+    pulls_player: bool,
+}
+
+impl GravityWell {
+    pub fn new(
+        pos: Point2<f32>,
+        dim: Vector2<f32>,
+        vel: Vector2<f32>,
+        color: Color,
+        radius: f32,
+        strength: f32,
+        pulls_player: bool,
+    ) -> Self {
+        Self {pos,dim,vel,color,radius,strength,pulls_player}
+    }
+
+    /// Force on something at `pos`, if it is in range, reaching across the
+    /// edges of `arena` if it wraps.
+    fn force_at(&self, arena: &Arena, pos: Point2<f32>) -> Option<Vector2<f32>> {
+        let to_center = arena.shortest(self.pos - pos);
+        let distance = to_center.norm();
+        if distance >= self.radius || distance == 0.0 {
+            return None;
+        }
+        let falloff = 1.0 - distance / self.radius;
+        Some(to_center / distance * self.strength * falloff)
+    }
+}
+
+impl LoadActor for GravityWell {
+    const TYPE_NAME: &'static str = "gravity_well";
+
+    fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(Self {
+            pos: r.read_finite_point()?,
+            dim: r.read_finite_vector()?,
+            vel: r.read_finite_vector()?,
+            color: r.read_color()?,
+            radius: r.read_f32()?,
+            strength: r.read_f32()?,
+            pulls_player: r.read_u8()? != 0,
+        })
+    }
+}
+
+impl Actor for GravityWell {
+    fn type_name(&self) -> &'static str {
+        Self::TYPE_NAME
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["hazard", "well"]
+    }
+
+    #[inline]
+    fn get_pos(&self) -> Point2<f32> {
+        self.pos
+    }
+
+    #[inline]
+    fn get_rect(&self) -> Rect {
+        Rect{
+            x: self.pos.x - self.dim.x / 2.0,
+            y: self.pos.y - self.dim.y / 2.0,
+            w: self.dim.x,
+            h: self.dim.y,
+        }
+    }
+
+    #[inline]
+    fn get_vel(&self) -> Vector2<f32> {
+        self.vel
+    }
+
+    fn shape(&self) -> Shape {
+        Shape::Circle {
+            center: self.pos,
+            radius: self.dim.x.min(self.dim.y) / 2.0,
+        }
+    }
+
+    #[inline]
+    fn set_pos(&mut self, pos: Point2<f32>) {
+        self.pos = pos
+    }
+
+    #[inline]
+    fn set_dim(&mut self, dim: Vector2<f32>) {
+        self.dim = dim
+    }
+
+    #[inline]
+    fn set_vel(&mut self, vel: Vector2<f32>) {
+        self.vel = vel
+    }
+
+    fn write_state(&self, w: &mut StateWriter) {
+        w.write_point(self.pos);
+        w.write_vector(self.dim);
+        w.write_vector(self.vel);
+        w.write_color(self.color);
+        w.write_f32(self.radius);
+        w.write_f32(self.strength);
+        w.write_u8(self.pulls_player as u8);
+    }
+
+    fn has_action(&self) -> bool { true }
+    fn sense(
+        &self,
+        _: ActorId,
+        world: &World,
+        _: &mut GameRng,
+    ) -> Action {
+        let mut commands: Vec<Command> = world
+            .enemies_in_radius(self.pos, self.radius)
+            .filter(|&(id, _)| world.enemies.has_tag(id, "bullet"))
+            .filter_map(|(id, enemy)| {
+                self.force_at(world.arena, enemy.get_pos())
+                    .map(|force| Command::Push(id, force))
+            })
+            .collect();
+        if self.pulls_player {
+            if let Some(force) = self.force_at(world.arena, world.player.get_pos()) {
+                commands.push(Command::Push(ActorId::PLAYER, force));
+            }
+        }
+        Action {
+            intent: None,
+            commands,
+        }
+    }
+
+    fn draw(
+        &self,
+        ctx: &mut ggez::Context,
+        mesh_builder: Option<&mut MeshBuilder>
+    ) -> ggez::GameResult
+    {
+        let field = Color { a: self.color.a * 0.15, ..self.color };
+        let core = self.dim.x.min(self.dim.y) / 2.0;
+        if let Some(mesh_builder) = mesh_builder {
+            mesh_builder.circle(DrawMode::stroke(1.0), self.pos, self.radius, 0.5, field);
+            mesh_builder.circle(DrawMode::fill(), self.pos, core, 0.5, self.color);
+        }
+        else {
+            let mesh = MeshBuilder::new()
+                .circle(DrawMode::stroke(1.0), self.pos, self.radius, 0.5, field)
+                .circle(DrawMode::fill(), self.pos, core, 0.5, self.color)
+                .build(ctx)?;
+            ggez::graphics::draw(
+                ctx,
+                &mesh,
+                DrawParam::default(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,11 +1,26 @@
 use super::*;
-use std::sync::Mutex;
+use std::f32::consts::TAU;
+
+use crate::archetype::Archetype;
+
+/// Sent from `HomingBullet::sense` to `HomingBullet::apply`.
+struct Steer {
+    target: Point2<f32>,
+    /// Whether its escort went out with the commands of the same `sense`.
+    escort_sent: bool,
+}
 
 pub struct HomingBullet {
     pos: Point2<f32>,
     dim: Vector2<f32>,
     vel: Vector2<f32>,
-    target: Mutex<Point2<f32>>,
+    target: Point2<f32>,
+    color: Color,
+    /// How hard it steers, in pixels per second squared.
+    homing_factor: f32,
+    speed_limit: f32,
+    /// Orbiters it sends out around itself once it is in, and how many.
+    escort: Option<(Archetype, u32)>,
 }
 
 impl HomingBullet {
@@ -14,15 +29,59 @@ impl HomingBullet {
         dim: Vector2<f32>, 
         vel: Vector2<f32>,
         target: Point2<f32>,
+        color: Color,
+        homing_factor: f32,
+        speed_limit: f32,
     ) -> Self {
         Self {
-            pos,dim,vel,
-            target: Mutex::new(target)
+            pos,dim,vel,target,color,homing_factor,speed_limit,
+            escort: None,
         }
     }
+
+    /// Has it come in with `count` orbiters of the `orbiter` archetype
+    /// circling it.
+    pub fn with_escort(mut self, orbiter: Archetype, count: u32) -> Self {
+        self.escort = Some((orbiter, count));
+        self
+    }
+}
+
+impl LoadActor for HomingBullet {
+    const TYPE_NAME: &'static str = "homing_bullet";
+
+    fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        let homing = Self {
+            pos: r.read_finite_point()?,
+            dim: r.read_finite_vector()?,
+            vel: r.read_finite_vector()?,
+            target: r.read_finite_point()?,
+            color: r.read_color()?,
+            homing_factor: r.read_f32()?,
+            speed_limit: r.read_f32()?,
+            escort: match r.read_u8()? {
+                0 => None,
+                _ => Some((Archetype::read_state(r)?, r.read_u32()?)),
+            },
+        };
+        if let Some((orbiter, _)) = &homing.escort {
+            if !orbiter.is_orbiter() {
+                return Err(state::invalid(format!("homing bullet escorted by {}", orbiter.name)));
+            }
+        }
+        Ok(homing)
+    }
 }
 
 impl Actor for HomingBullet {
+    fn type_name(&self) -> &'static str {
+        Self::TYPE_NAME
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["hazard", "bullet", "homing"]
+    }
+
     #[inline]
     fn get_pos(&self) -> Point2<f32> {
         self.pos
@@ -58,27 +117,72 @@ impl Actor for HomingBullet {
         self.vel = vel
     }
 
+    fn write_state(&self, w: &mut StateWriter) {
+        w.write_point(self.pos);
+        w.write_vector(self.dim);
+        w.write_vector(self.vel);
+        w.write_point(self.target);
+        w.write_color(self.color);
+        w.write_f32(self.homing_factor);
+        w.write_f32(self.speed_limit);
+        match &self.escort {
+            None => w.write_u8(0),
+            Some((orbiter, count)) => {
+                w.write_u8(1);
+                orbiter.write_state(w);
+                w.write_u32(*count);
+            }
+        }
+    }
+
     fn has_action(&self) -> bool { true }
-    fn action(
+    fn sense(
         &self,
-        _dt: f32,
-        player: &Player,
-        _: &[Box<dyn Actor>],
-    ) -> Option<Vec<Box<dyn Actor>>> {
-        *self.target.lock().unwrap() = player.get_pos();
-        None
+        id: ActorId,
+        world: &World,
+        rng: &mut GameRng,
+    ) -> Action {
+        let mut commands = vec![];
+        let escort_sent = self.escort.is_some();
+        if let Some((orbiter, count)) = &self.escort {
+            for i in 0..*count {
+                let (size, speed) = orbiter.roll(rng);
+                commands.extend(orbiter.orbiter(
+                    Anchor::Actor(id),
+                    self.pos,
+                    TAU * i as f32 / *count as f32,
+                    0.0,
+                    size,
+                    speed,
+                ).map(Command::Spawn));
+            }
+        }
+        Action {
+            intent: Some(Box::new(Steer {
+                target: world.player.get_pos(),
+                escort_sent,
+            })),
+            commands,
+        }
+    }
+
+    fn apply(&mut self, intent: Intent) {
+        if let Ok(steer) = intent.downcast::<Steer>() {
+            self.target = steer.target;
+            if steer.escort_sent {
+                self.escort = None;
+            }
+        }
     }
 
     fn update(&mut self, dt: f32) {
-        const HOMING_FACTOR: f32 = 50.0;
-        const SPEED_LIMIT: f32 = 100.0;
-        let diff = *self.target.lock().unwrap() - self.pos;
-        let acc = diff.normalize() * HOMING_FACTOR;
+        let diff = self.target - self.pos;
+        let acc = diff.normalize() * self.homing_factor;
         self.vel += acc * dt;
         let speed_sqr =
             self.vel.x * self.vel.x + self.vel.y * self.vel.y;
-        if speed_sqr > SPEED_LIMIT * SPEED_LIMIT {
-            self.vel = self.vel.normalize() * SPEED_LIMIT;
+        if speed_sqr > self.speed_limit * self.speed_limit {
+            self.vel = self.vel.normalize() * self.speed_limit;
         }
         self.add_pos(self.vel * dt);
     }
@@ -94,7 +198,7 @@ impl Actor for HomingBullet {
             mesh_builder.rectangle(
                 DrawMode::fill(),
                 rect,
-                [0.0, 0.5, 1.0, 1.0].into(),
+                self.color,
             );
         }
         else {
@@ -102,7 +206,7 @@ impl Actor for HomingBullet {
                 ctx,
                 DrawMode::fill(),
                 rect,
-                [0.0, 0.5, 1.0, 1.0].into(),
+                self.color,
             )?;
             ggez::graphics::draw(
                 ctx,
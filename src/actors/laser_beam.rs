@@ -0,0 +1,194 @@
+use super::*;
+
+/// A line across the arena that flashes as a warning for `warning` seconds,
+/// then kills on touch for `duration` seconds before it fades away.
+pub struct LaserBeam {
+    a: Point2<f32>,
+    b: Point2<f32>,
+    /// Half the width of the beam once it fires.
+    radius: f32,
+    color: Color,
+    warning: f32,
+    duration: f32,
+    age: f32,
+}
+
+impl LaserBeam {
+    /// Flashes per second while it warns.
+    const FLASH_RATE: f32 = 6.0;
+    const WARNING_WIDTH: f32 = 1.5;
+
+    /// A beam along the line through `from` and `towards`, reaching from edge
+    /// to edge of `arena` without sticking out of it, so that the arena's
+    /// boundary never has to move it.
+    pub fn across(
+        arena: &Arena,
+        from: Point2<f32>,
+        towards: Point2<f32>,
+        width: f32,
+        color: Color,
+        warning: f32,
+        duration: f32,
+    ) -> Self {
+        let radius = width / 2.0;
+        // Keeps rounding from leaving the ends a hair outside.
+        let inset = radius + 1.0;
+        let dir = towards - from;
+        let dir = if dir.norm() > 0.0 { dir.normalize() } else { Vector2::new(1.0, 0.0) };
+        // Where the line enters and leaves the arena shrunk by `inset`.
+        let mut t_min = f32::NEG_INFINITY;
+        let mut t_max = f32::INFINITY;
+        for (axis, size) in [arena.width, arena.height].iter().enumerate() {
+            let (lo, hi) = (inset, (size - inset).max(inset));
+            if dir[axis] == 0.0 {
+                continue;
+            }
+            let t_lo = (lo - from[axis]) / dir[axis];
+            let t_hi = (hi - from[axis]) / dir[axis];
+            t_min = t_min.max(t_lo.min(t_hi));
+            t_max = t_max.min(t_lo.max(t_hi));
+        }
+        let clamp = |p: Point2<f32>| Point2::new(
+            p.x.clamp(inset, (arena.width - inset).max(inset)),
+            p.y.clamp(inset, (arena.height - inset).max(inset)),
+        );
+        let (a, b) = if t_min <= t_max {
+            (from + dir * t_min, from + dir * t_max)
+        }
+        else {
+            (from, from)
+        };
+        Self {
+            a: clamp(a),
+            b: clamp(b),
+            radius,
+            color,
+            warning,
+            duration,
+            age: 0.0,
+        }
+    }
+
+    fn is_firing(&self) -> bool {
+        self.age >= self.warning
+    }
+}
+
+impl LoadActor for LaserBeam {
+    const TYPE_NAME: &'static str = "laser_beam";
+
+    fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(Self {
+            a: r.read_finite_point()?,
+            b: r.read_finite_point()?,
+            radius: r.read_f32()?,
+            color: r.read_color()?,
+            warning: r.read_f32()?,
+            duration: r.read_f32()?,
+            age: r.read_f32()?,
+        })
+    }
+}
+
+impl Actor for LaserBeam {
+    fn type_name(&self) -> &'static str {
+        Self::TYPE_NAME
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["hazard", "laser"]
+    }
+
+    #[inline]
+    fn get_pos(&self) -> Point2<f32> {
+        na::center(&self.a, &self.b)
+    }
+
+    #[inline]
+    fn get_rect(&self) -> Rect {
+        self.shape().bounds()
+    }
+
+    #[inline]
+    fn get_vel(&self) -> Vector2<f32> {
+        Vector2::new(0.0, 0.0)
+    }
+
+    /// The beam is drawn as a line with square ends, so it is hit as a box
+    /// along it rather than a capsule.
+    fn shape(&self) -> Shape {
+        let along = self.b - self.a;
+        Shape::Obb {
+            center: self.get_pos(),
+            half_extents: Vector2::new(along.norm() / 2.0, self.radius),
+            angle: along.y.atan2(along.x),
+        }
+    }
+
+    fn is_lethal(&self) -> bool {
+        self.is_firing()
+    }
+
+    fn set_pos(&mut self, pos: Point2<f32>) {
+        let diff = pos - self.get_pos();
+        self.a += diff;
+        self.b += diff;
+    }
+
+    /// Only the width of the beam can change.
+    #[inline]
+    fn set_dim(&mut self, dim: Vector2<f32>) {
+        self.radius = dim.x.min(dim.y) / 2.0
+    }
+
+    #[inline]
+    fn set_vel(&mut self, _: Vector2<f32>) {}
+
+    fn write_state(&self, w: &mut StateWriter) {
+        w.write_point(self.a);
+        w.write_point(self.b);
+        w.write_f32(self.radius);
+        w.write_color(self.color);
+        w.write_f32(self.warning);
+        w.write_f32(self.duration);
+        w.write_f32(self.age);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.age += dt;
+    }
+
+    fn draw(
+        &self,
+        ctx: &mut ggez::Context,
+        mesh_builder: Option<&mut MeshBuilder>
+    ) -> ggez::GameResult
+    {
+        if self.a == self.b {
+            return Ok(());
+        }
+        let (width, color) = if self.is_firing() {
+            (self.radius * 2.0, self.color)
+        }
+        else if (self.age * Self::FLASH_RATE).fract() < 0.5 {
+            (Self::WARNING_WIDTH, Color { a: self.color.a * 0.6, ..self.color })
+        }
+        else {
+            (Self::WARNING_WIDTH, Color { a: self.color.a * 0.2, ..self.color })
+        };
+        let points = [self.a, self.b];
+        if let Some(mesh_builder) = mesh_builder {
+            mesh_builder.line(&points, width, color)?;
+        }
+        else {
+            let mesh = Mesh::new_line(ctx, &points, width, color)?;
+            ggez::graphics::draw(
+                ctx,
+                &mesh,
+                DrawParam::default(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
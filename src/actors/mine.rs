@@ -0,0 +1,287 @@
+use super::*;
+
+/// Where a `Mine` is in its life.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Fuse {
+    /// Harmless, with this many seconds left until it is armed.
+    Arming(f32),
+    /// Waiting for the player to come within its trigger radius.
+    Armed,
+    /// Exploded this many seconds ago.
+    Blasting(f32),
+}
+
+/// Sent to `Mine::apply` when the player or another mine's blast sets it
+/// off.
+struct Triggered;
+
+/// Sits still, arms after `arm_time` seconds, and explodes once the player
+/// comes within `trigger_radius`. The blast grows to `blast_radius` over
+/// `blast_time` seconds, killing on touch while it grows, taking any bullets
+/// it reaches with it and setting off any armed mines.
+pub struct Mine {
+    pos: Point2<f32>,
+    dim: Vector2<f32>,
+    color: Color,
+    trigger_radius: f32,
+    blast_radius: f32,
+    blast_time: f32,
+    fuse: Fuse,
+}
+
+impl Mine {
+    pub fn new(
+        pos: Point2<f32>,
+        dim: Vector2<f32>,
+        color: Color,
+        arm_time: f32,
+        trigger_radius: f32,
+        blast_radius: f32,
+        blast_time: f32,
+    ) -> Self {
+        Self {
+            pos,dim,color,trigger_radius,blast_radius,blast_time,
+            fuse: Fuse::Arming(arm_time),
+        }
+    }
+
+    /// Current radius of the blast, if it went off.
+    fn blast(&self) -> Option<f32> {
+        match self.fuse {
+            Fuse::Blasting(elapsed) => {
+                Some(self.blast_radius * (elapsed / self.blast_time).min(1.0))
+            }
+            _ => None,
+        }
+    }
+
+    /// Outlined while it arms, filled with its trigger radius around it once
+    /// armed, and with the blast on top once it went off.
+    fn add_to_mesh(&self, mesh_builder: &mut MeshBuilder) {
+        let rect = self.get_rect();
+        if let Fuse::Arming(_) = self.fuse {
+            mesh_builder.rectangle(DrawMode::stroke(1.0), rect, self.color);
+            return;
+        }
+        mesh_builder.rectangle(DrawMode::fill(), rect, self.color);
+        match self.blast() {
+            Some(radius) => {
+                if radius > 0.0 {
+                    mesh_builder.circle(
+                        DrawMode::fill(),
+                        self.pos,
+                        radius,
+                        0.5,
+                        Color { a: self.color.a * 0.6, ..self.color },
+                    );
+                }
+            }
+            None => {
+                mesh_builder.circle(
+                    DrawMode::stroke(1.0),
+                    self.pos,
+                    self.trigger_radius,
+                    0.5,
+                    Color { a: self.color.a * 0.3, ..self.color },
+                );
+            }
+        }
+    }
+}
+
+impl LoadActor for Mine {
+    const TYPE_NAME: &'static str = "mine";
+
+    fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(Self {
+            pos: r.read_finite_point()?,
+            dim: r.read_finite_vector()?,
+            color: r.read_color()?,
+            trigger_radius: r.read_f32()?,
+            blast_radius: r.read_f32()?,
+            blast_time: r.read_f32()?,
+            fuse: match r.read_u8()? {
+                0 => Fuse::Arming(r.read_f32()?),
+                1 => Fuse::Armed,
+                2 => Fuse::Blasting(r.read_f32()?),
+                v => return Err(state::invalid(format!("invalid fuse {}", v))),
+            },
+        })
+    }
+}
+
+impl Actor for Mine {
+    fn type_name(&self) -> &'static str {
+        Self::TYPE_NAME
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["hazard", "mine"]
+    }
+
+    #[inline]
+    fn get_pos(&self) -> Point2<f32> {
+        self.pos
+    }
+
+    /// Only the mine itself, so that a blast reaching past the edges of the
+    /// arena doesn't get it moved.
+    #[inline]
+    fn get_rect(&self) -> Rect {
+        Rect{
+            x: self.pos.x - self.dim.x / 2.0,
+            y: self.pos.y - self.dim.y / 2.0,
+            w: self.dim.x,
+            h: self.dim.y,
+        }
+    }
+
+    #[inline]
+    fn get_vel(&self) -> Vector2<f32> {
+        Vector2::new(0.0, 0.0)
+    }
+
+    fn shape(&self) -> Shape {
+        match self.blast() {
+            Some(radius) => Shape::Circle {
+                center: self.pos,
+                radius,
+            },
+            None => Shape::Aabb(self.get_rect()),
+        }
+    }
+
+    fn is_lethal(&self) -> bool {
+        matches!(self.fuse, Fuse::Blasting(elapsed) if elapsed < self.blast_time)
+    }
+
+    #[inline]
+    fn set_pos(&mut self, pos: Point2<f32>) {
+        self.pos = pos
+    }
+
+    #[inline]
+    fn set_dim(&mut self, dim: Vector2<f32>) {
+        self.dim = dim
+    }
+
+    #[inline]
+    fn set_vel(&mut self, _: Vector2<f32>) {}
+
+    fn write_state(&self, w: &mut StateWriter) {
+        w.write_point(self.pos);
+        w.write_vector(self.dim);
+        w.write_color(self.color);
+        w.write_f32(self.trigger_radius);
+        w.write_f32(self.blast_radius);
+        w.write_f32(self.blast_time);
+        match self.fuse {
+            Fuse::Arming(left) => {
+                w.write_u8(0);
+                w.write_f32(left);
+            }
+            Fuse::Armed => w.write_u8(1),
+            Fuse::Blasting(elapsed) => {
+                w.write_u8(2);
+                w.write_f32(elapsed);
+            }
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.fuse = match self.fuse {
+            Fuse::Arming(left) if left > dt => Fuse::Arming(left - dt),
+            Fuse::Arming(_) => Fuse::Armed,
+            Fuse::Armed => Fuse::Armed,
+            Fuse::Blasting(elapsed) => Fuse::Blasting(elapsed + dt),
+        };
+    }
+
+    fn has_action(&self) -> bool { true }
+    fn sense(
+        &self,
+        id: ActorId,
+        world: &World,
+        _: &mut GameRng,
+    ) -> Action {
+        match (self.fuse, self.blast()) {
+            (Fuse::Armed, _) => {
+                let trigger = Shape::Circle {
+                    center: self.pos,
+                    radius: self.trigger_radius,
+                };
+                let player = world.player.shape();
+                let triggered = world.arena
+                    .offsets(
+                        world.arena.boundary_with_player(self),
+                        trigger.bounds(),
+                        player.bounds(),
+                    )
+                    .into_iter()
+                    .any(|offset| trigger.intersects(&player.translate(offset)));
+                if triggered {
+                    Action {
+                        intent: Some(Box::new(Triggered)),
+                        commands: vec![],
+                    }
+                }
+                else {
+                    Action::default()
+                }
+            }
+            (Fuse::Blasting(elapsed), _) if elapsed >= self.blast_time => Action {
+                intent: None,
+                commands: vec![Command::DespawnSelf],
+            },
+            (Fuse::Blasting(_), Some(radius)) => Action {
+                intent: None,
+                commands: world
+                    .enemies_in_radius(self.pos, radius)
+                    .filter_map(|(other, _)| {
+                        if world.enemies.has_tag(other, "bullet") {
+                            Some(Command::Despawn(other))
+                        }
+                        else if other != id && world.enemies.has_tag(other, "mine") {
+                            Some(Command::Modify(other, Box::new(|mine| {
+                                mine.apply(Box::new(Triggered))
+                            })))
+                        }
+                        else {
+                            None
+                        }
+                    })
+                    .collect(),
+            },
+            _ => Action::default(),
+        }
+    }
+
+    fn apply(&mut self, intent: Intent) {
+        if intent.is::<Triggered>() && self.fuse == Fuse::Armed {
+            self.fuse = Fuse::Blasting(0.0);
+        }
+    }
+
+    fn draw(
+        &self,
+        ctx: &mut ggez::Context,
+        mesh_builder: Option<&mut MeshBuilder>
+    ) -> ggez::GameResult
+    {
+        if let Some(mesh_builder) = mesh_builder {
+            self.add_to_mesh(mesh_builder);
+        }
+        else {
+            let mesh_builder = &mut MeshBuilder::new();
+            self.add_to_mesh(mesh_builder);
+            let mesh = mesh_builder.build(ctx)?;
+            ggez::graphics::draw(
+                ctx,
+                &mesh,
+                DrawParam::default(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
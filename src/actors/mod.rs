@@ -1,23 +1,78 @@
+use std::any::Any;
+use std::collections::HashMap;
+
 use ggez::nalgebra as na;
 use na::{Point2, Vector2};
 use ggez::graphics::{
-    self, Rect, DrawMode, DrawParam, Mesh, MeshBuilder,
+    self, Color, Rect, DrawMode, DrawParam, Mesh, MeshBuilder,
 };
 
+use crate::arena::{Arena, Boundary};
+use crate::collision::Shape;
+use crate::lifetime::Expiry;
+use crate::rng::GameRng;
+use crate::spatial::SpatialGrid;
+use crate::state::{self, StateReader, StateWriter};
+
 mod player;
 mod bullet;
 mod drunk_bullet;
 mod homing_bullet;
+mod bouncing_bullet;
+mod laser_beam;
+mod splitter_bullet;
+mod mine;
+mod gravity_well;
+mod orbiter_bullet;
+mod emitter;
+mod set;
 pub use player::Player;
 pub use bullet::Bullet;
 pub use drunk_bullet::DrunkBullet;
 pub use homing_bullet::HomingBullet;
+pub use bouncing_bullet::{BouncingBullet, AfterBounces};
+pub use laser_beam::LaserBeam;
+pub use splitter_bullet::{SplitterBullet, Fragments};
+pub use mine::Mine;
+pub use gravity_well::GravityWell;
+pub use orbiter_bullet::{OrbiterBullet, Anchor, Orbit};
+pub use emitter::{Emitter, Pattern};
+pub use set::ActorSet;
 
 pub trait Actor: Send + Sync {
+    /// Key of the actor's loader in the type registry.
+    fn type_name(&self) -> &'static str;
+
+    /// Labels for finding actors by role, such as "hazard" or "homing",
+    /// that every actor of the type has. A `Spawn` can add more.
+    fn tags(&self) -> &'static [&'static str] {
+        &[]
+    }
+
     fn get_pos(&self) -> Point2<f32>;
     fn get_rect(&self) -> Rect;
     fn get_vel(&self) -> Vector2<f32>;
 
+    /// Area the actor can be hit in. Defaults to its whole rect.
+    fn shape(&self) -> Shape {
+        Shape::Aabb(self.get_rect())
+    }
+
+    /// What the edges of `arena` do to the actor. Defaults to the arena's
+    /// rule for enemies.
+    fn boundary(&self, arena: &Arena) -> Boundary {
+        arena.enemies
+    }
+
+    /// Called when an edge of the arena turned the actor back with
+    /// `Boundary::Bounce`.
+    fn on_bounce(&mut self) {}
+
+    /// Whether touching the actor kills the player right now.
+    fn is_lethal(&self) -> bool {
+        true
+    }
+
     fn set_pos(&mut self, pos: Point2<f32>);
     fn set_dim(&mut self, dim: Vector2<f32>);
     fn set_vel(&mut self, vel: Vector2<f32>);
@@ -26,6 +81,10 @@ pub trait Actor: Send + Sync {
         self.set_pos(self.get_pos() + diff)
     }
 
+    /// Writes every field that affects the simulation, including private
+    /// ones, so that checksums catch any divergence.
+    fn write_state(&self, w: &mut StateWriter);
+
     fn update(
         &mut self,
         dt: f32,
@@ -34,16 +93,162 @@ pub trait Actor: Send + Sync {
     }
 
     fn has_action(&self) -> bool { false }
-    fn action(
+    /// Looks at the world and decides what to do this tick. `id` is the
+    /// acting actor's own.
+    ///
+    /// Every actor senses in parallel, so this only gets to read. Changes
+    /// to the actor itself go in `Action::intent`, to be made in `apply`.
+    fn sense(
         &self,
-        _dt: f32,
-        _player: &Player,
-        _current_enemies: &[Box<dyn Actor>],
-    ) -> Option<Vec<Box<dyn Actor>>> { None }
+        _id: ActorId,
+        _world: &World,
+        _rng: &mut GameRng,
+    ) -> Action { Action::default() }
+    /// Carries out the intent returned by `sense`, once every actor has
+    /// sensed.
+    fn apply(&mut self, _intent: Intent) {}
     
     fn draw(
         &self,
         ctx: &mut ggez::Context,
         mesh_builder: Option<&mut MeshBuilder>
     ) -> ggez::GameResult;
-}
\ No newline at end of file
+}
+
+/// Identifies an actor for as long as it exists, across ticks and
+/// snapshots.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ActorId(pub u64);
+
+impl ActorId {
+    pub const PLAYER: ActorId = ActorId(0);
+}
+
+/// Change applied to an actor by `Command::Modify`.
+pub type Modifier = Box<dyn FnOnce(&mut dyn Actor) + Send>;
+
+/// An enemy on its way into the game, along with when it should go again.
+pub struct Spawn {
+    pub actor: Box<dyn Actor>,
+    pub expiry: Expiry,
+    /// Tags on top of the actor's own `Actor::tags`.
+    pub tags: Vec<String>,
+}
+
+/// Whatever an actor wants to remember from `Actor::sense` until
+/// `Actor::apply`, which downcasts it back to its own type.
+pub type Intent = Box<dyn Any + Send>;
+
+/// What an actor decided to do in `Actor::sense`.
+#[derive(Default)]
+pub struct Action {
+    /// Handed back to the actor's `Actor::apply`.
+    pub intent: Option<Intent>,
+    /// Changes to the rest of the game.
+    pub commands: Vec<Command>,
+}
+
+/// A change to the game requested by `Actor::sense`. Commands are applied
+/// in order once every actor has applied its intent.
+pub enum Command {
+    /// Adds a new enemy.
+    Spawn(Spawn),
+    /// Removes the acting actor.
+    DespawnSelf,
+    /// Removes an enemy, unless it is already gone. The player can't be
+    /// despawned.
+    Despawn(ActorId),
+    /// Runs a function on an actor, unless it is already gone.
+    Modify(ActorId, Modifier),
+    /// Accelerates an actor by this many pixels per second squared for the
+    /// tick. Pushes on the same actor add up before any of them is applied,
+    /// so fields overlapping it don't depend on the order they sensed in.
+    Push(ActorId, Vector2<f32>),
+}
+
+/// Read-only view of the game handed to `Actor::sense`.
+pub struct World<'a> {
+    pub player: &'a Player,
+    pub enemies: &'a ActorSet,
+    pub grid: &'a SpatialGrid,
+    pub arena: &'a Arena,
+}
+
+impl<'a> World<'a> {
+    /// The enemy with `id`, unless it is gone or fading out.
+    pub fn enemy(&self, id: ActorId) -> Option<&'a dyn Actor> {
+        if self.enemies.life(id)?.is_fading() {
+            return None;
+        }
+        self.enemies.get(id)
+    }
+
+    /// Enemies whose shape overlaps `rect`.
+    #[allow(dead_code)]
+    pub fn enemies_in_rect(
+        &self,
+        rect: Rect,
+    ) -> impl Iterator<Item = (ActorId, &'a dyn Actor)> {
+        let (enemies, ids) = (self.enemies.actors(), self.enemies.ids());
+        self.grid.query_rect(rect)
+            .into_iter()
+            .map(move |i| (ids[i], enemies[i].as_ref()))
+    }
+
+    /// Enemies whose shape comes within `radius` of `center`.
+    pub fn enemies_in_radius(
+        &self,
+        center: Point2<f32>,
+        radius: f32,
+    ) -> impl Iterator<Item = (ActorId, &'a dyn Actor)> {
+        let (enemies, ids) = (self.enemies.actors(), self.enemies.ids());
+        self.grid.query_radius(center, radius)
+            .into_iter()
+            .map(move |i| (ids[i], enemies[i].as_ref()))
+    }
+}
+
+/// Actors that can be rebuilt from what their `Actor::write_state` wrote.
+pub trait LoadActor: Actor + Sized + 'static {
+    const TYPE_NAME: &'static str;
+
+    fn read_state(r: &mut StateReader) -> ggez::GameResult<Self>;
+}
+
+type ActorLoader = fn(&mut StateReader) -> ggez::GameResult<Box<dyn Actor>>;
+
+fn load<A: LoadActor>(r: &mut StateReader) -> ggez::GameResult<Box<dyn Actor>> {
+    Ok(Box::new(A::read_state(r)?))
+}
+
+lazy_static! {
+    /// Every enemy type that can appear in a saved game.
+    static ref ACTOR_TYPES: HashMap<&'static str, ActorLoader> = {
+        let mut types: HashMap<&'static str, ActorLoader> = HashMap::new();
+        types.insert(Bullet::TYPE_NAME, load::<Bullet>);
+        types.insert(DrunkBullet::TYPE_NAME, load::<DrunkBullet>);
+        types.insert(HomingBullet::TYPE_NAME, load::<HomingBullet>);
+        types.insert(BouncingBullet::TYPE_NAME, load::<BouncingBullet>);
+        types.insert(LaserBeam::TYPE_NAME, load::<LaserBeam>);
+        types.insert(SplitterBullet::TYPE_NAME, load::<SplitterBullet>);
+        types.insert(Mine::TYPE_NAME, load::<Mine>);
+        types.insert(GravityWell::TYPE_NAME, load::<GravityWell>);
+        types.insert(OrbiterBullet::TYPE_NAME, load::<OrbiterBullet>);
+        types.insert(Emitter::TYPE_NAME, load::<Emitter>);
+        types
+    };
+}
+
+/// Writes `actor` along with its type, for `read_actor` to rebuild it.
+pub fn write_actor(actor: &dyn Actor, w: &mut StateWriter) {
+    w.write_str(actor.type_name());
+    actor.write_state(w);
+}
+
+pub fn read_actor(r: &mut StateReader) -> ggez::GameResult<Box<dyn Actor>> {
+    let type_name = r.read_str()?;
+    match ACTOR_TYPES.get(type_name) {
+        Some(loader) => loader(r),
+        None => Err(state::invalid(format!("unknown actor type {:?}", type_name))),
+    }
+}
@@ -0,0 +1,316 @@
+use super::*;
+use std::f32::consts::TAU;
+
+/// What an `OrbiterBullet` circles around.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    /// An enemy, followed for as long as it is around.
+    Actor(ActorId),
+    /// A fixed spot, such as where the player was.
+    Point(Point2<f32>),
+}
+
+impl Anchor {
+    pub fn write_state(&self, w: &mut StateWriter) {
+        match *self {
+            Anchor::Actor(id) => {
+                w.write_u8(0);
+                w.write_u64(id.0);
+            }
+            Anchor::Point(point) => {
+                w.write_u8(1);
+                w.write_point(point);
+            }
+        }
+    }
+
+    pub fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(match r.read_u8()? {
+            0 => Anchor::Actor(ActorId(r.read_u64()?)),
+            1 => Anchor::Point(r.read_finite_point()?),
+            v => return Err(state::invalid(format!("invalid anchor {}", v))),
+        })
+    }
+}
+
+/// The circle an `OrbiterBullet` settles into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Orbit {
+    pub radius: f32,
+    /// Radians per second once it is on its circle.
+    pub angular_speed: f32,
+    /// Pixels per second it closes in on, or moves out to, its circle with.
+    pub speed: f32,
+}
+
+impl Orbit {
+    pub fn write_state(&self, w: &mut StateWriter) {
+        w.write_f32(self.radius);
+        w.write_f32(self.angular_speed);
+        w.write_f32(self.speed);
+    }
+
+    pub fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(Self {
+            radius: r.read_f32()?,
+            angular_speed: r.read_f32()?,
+            speed: r.read_f32()?,
+        })
+    }
+}
+
+/// Sent from `OrbiterBullet::sense` to `OrbiterBullet::apply`.
+enum Tether {
+    /// How far it is off its circle and how fast the anchor moves.
+    Follow(Vector2<f32>, Vector2<f32>),
+    /// The anchor is gone.
+    Release,
+}
+
+/// Spirals out or in to an `Orbit` around its `Anchor` and keeps circling
+/// it. Once the anchor despawns it flies off along the way it was going.
+pub struct OrbiterBullet {
+    pos: Point2<f32>,
+    dim: Vector2<f32>,
+    vel: Vector2<f32>,
+    color: Color,
+    /// `None` once it has been let go.
+    anchor: Option<Anchor>,
+    anchor_vel: Vector2<f32>,
+    /// Direction from the anchor, in radians.
+    angle: f32,
+    distance: f32,
+    orbit: Orbit,
+}
+
+impl OrbiterBullet {
+    /// Starts `distance` pixels from `center`, where `anchor` is now, in the
+    /// direction of `angle`.
+    pub fn new(
+        anchor: Anchor,
+        center: Point2<f32>,
+        angle: f32,
+        distance: f32,
+        dim: Vector2<f32>,
+        color: Color,
+        orbit: Orbit,
+    ) -> Self {
+        let mut orbiter = Self {
+            pos: center,
+            dim,
+            vel: Vector2::new(0.0, 0.0),
+            color,
+            anchor: Some(anchor),
+            anchor_vel: Vector2::new(0.0, 0.0),
+            angle,
+            distance,
+            orbit,
+        };
+        orbiter.pos += orbiter.offset();
+        orbiter
+    }
+
+    /// Where it should be relative to its anchor.
+    fn offset(&self) -> Vector2<f32> {
+        Vector2::new(self.angle.cos(), self.angle.sin()) * self.distance
+    }
+}
+
+impl LoadActor for OrbiterBullet {
+    const TYPE_NAME: &'static str = "orbiter_bullet";
+
+    fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(Self {
+            pos: r.read_finite_point()?,
+            dim: r.read_finite_vector()?,
+            vel: r.read_finite_vector()?,
+            color: r.read_color()?,
+            anchor: match r.read_u8()? {
+                0 => None,
+                _ => Some(Anchor::read_state(r)?),
+            },
+            anchor_vel: r.read_finite_vector()?,
+            angle: r.read_f32()?,
+            distance: r.read_f32()?,
+            orbit: Orbit::read_state(r)?,
+        })
+    }
+}
+
+impl Actor for OrbiterBullet {
+    fn type_name(&self) -> &'static str {
+        Self::TYPE_NAME
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["hazard", "bullet", "orbiter"]
+    }
+
+    #[inline]
+    fn get_pos(&self) -> Point2<f32> {
+        self.pos
+    }
+
+    #[inline]
+    fn get_rect(&self) -> Rect {
+        Rect{
+            x: self.pos.x - self.dim.x / 2.0,
+            y: self.pos.y - self.dim.y / 2.0,
+            w: self.dim.x,
+            h: self.dim.y,
+        }
+    }
+
+    #[inline]
+    fn get_vel(&self) -> Vector2<f32> {
+        self.vel
+    }
+
+    fn shape(&self) -> Shape {
+        Shape::Circle {
+            center: self.pos,
+            radius: self.dim.x.min(self.dim.y) / 2.0,
+        }
+    }
+
+    /// Kept inside the arena while it circles, since its circle may reach
+    /// past the edges.
+    fn boundary(&self, arena: &Arena) -> Boundary {
+        match (self.anchor, arena.enemies) {
+            (None, boundary) | (Some(_), boundary @ Boundary::Wrap) => boundary,
+            (Some(_), _) => Boundary::Clamp,
+        }
+    }
+
+    #[inline]
+    fn set_pos(&mut self, pos: Point2<f32>) {
+        self.pos = pos
+    }
+
+    #[inline]
+    fn set_dim(&mut self, dim: Vector2<f32>) {
+        self.dim = dim
+    }
+
+    /// Only takes once it has been let go.
+    #[inline]
+    fn set_vel(&mut self, vel: Vector2<f32>) {
+        if self.anchor.is_none() {
+            self.vel = vel
+        }
+    }
+
+    fn write_state(&self, w: &mut StateWriter) {
+        w.write_point(self.pos);
+        w.write_vector(self.dim);
+        w.write_vector(self.vel);
+        w.write_color(self.color);
+        match &self.anchor {
+            None => w.write_u8(0),
+            Some(anchor) => {
+                w.write_u8(1);
+                anchor.write_state(w);
+            }
+        }
+        w.write_vector(self.anchor_vel);
+        w.write_f32(self.angle);
+        w.write_f32(self.distance);
+        self.orbit.write_state(w);
+    }
+
+    fn update(&mut self, dt: f32) {
+        if self.anchor.is_none() {
+            self.add_pos(self.vel * dt);
+            return;
+        }
+        let start = self.offset();
+        let gap = self.orbit.radius - self.distance;
+        let step = self.orbit.speed * dt;
+        let radial_speed = if gap.abs() > step {
+            self.orbit.speed.copysign(gap)
+        }
+        else {
+            0.0
+        };
+        self.distance += step.min(gap.abs()).copysign(gap);
+        // Beyond its circle it goes around no faster than it will on it,
+        // rather than sweeping across half the arena.
+        let spin = self.orbit.angular_speed * self.orbit.radius
+            / self.distance.max(self.orbit.radius);
+        self.angle = (self.angle + spin * dt).rem_euclid(TAU);
+        let out = Vector2::new(self.angle.cos(), self.angle.sin());
+        let across = Vector2::new(-out.y, out.x);
+        self.vel = self.anchor_vel + out * radial_speed + across * spin * self.distance;
+        self.add_pos(self.anchor_vel * dt + self.offset() - start);
+    }
+
+    fn has_action(&self) -> bool {
+        self.anchor.is_some()
+    }
+
+    fn sense(
+        &self,
+        _: ActorId,
+        world: &World,
+        _: &mut GameRng,
+    ) -> Action {
+        let (center, vel) = match self.anchor {
+            Some(Anchor::Actor(id)) => match world.enemy(id) {
+                Some(parent) => (parent.get_pos(), parent.get_vel()),
+                None => return Action {
+                    intent: Some(Box::new(Tether::Release)),
+                    commands: vec![],
+                },
+            },
+            Some(Anchor::Point(point)) => (point, Vector2::new(0.0, 0.0)),
+            None => return Action::default(),
+        };
+        // The anchor may have wrapped around since, and the arena may have
+        // pushed this back in from the edge.
+        let correction = world.arena.shortest(center + self.offset() - self.pos);
+        Action {
+            intent: Some(Box::new(Tether::Follow(correction, vel))),
+            commands: vec![],
+        }
+    }
+
+    fn apply(&mut self, intent: Intent) {
+        match intent.downcast::<Tether>().map(|tether| *tether) {
+            Ok(Tether::Follow(correction, vel)) => {
+                self.add_pos(correction);
+                self.anchor_vel = vel;
+            }
+            Ok(Tether::Release) => self.anchor = None,
+            Err(_) => (),
+        }
+    }
+
+    fn draw(
+        &self,
+        ctx: &mut ggez::Context,
+        mesh_builder: Option<&mut MeshBuilder>
+    ) -> ggez::GameResult
+    {
+        let radius = self.dim.x.min(self.dim.y) / 2.0;
+        if let Some(mesh_builder) = mesh_builder {
+            mesh_builder.circle(DrawMode::fill(), self.pos, radius, 0.5, self.color);
+        }
+        else {
+            let mesh = Mesh::new_circle(
+                ctx,
+                DrawMode::fill(),
+                self.pos,
+                radius,
+                0.5,
+                self.color,
+            )?;
+            ggez::graphics::draw(
+                ctx,
+                &mesh,
+                DrawParam::default(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
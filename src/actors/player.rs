@@ -1,9 +1,18 @@
 use super::*;
 
+/// Radius of the player's hitbox, a bit smaller than the sprite so that
+/// grazing a bullet doesn't count as a hit.
+const HITBOX_RADIUS: f32 = 6.0;
+
+/// How quickly velocity pushed onto the player dies down, per second.
+const DRIFT_DRAG: f32 = 3.0;
+
 pub struct Player {
     pos: Point2<f32>,
     dim: Vector2<f32>,
     vel: Vector2<f32>,
+    /// Velocity from pushes, on top of what the input moves it by.
+    drift: Vector2<f32>,
 }
 
 impl Player {
@@ -12,12 +21,40 @@ impl Player {
         Self {
             pos,
             dim,
-            vel: Vector2::new(0.0, 0.0)
+            vel: Vector2::new(0.0, 0.0),
+            drift: Vector2::new(0.0, 0.0),
         }
     } 
+
+    #[inline]
+    pub fn drift(&self) -> Vector2<f32> {
+        self.drift
+    }
+
+    /// Adds `dv` to the player's drift.
+    pub fn push(&mut self, dv: Vector2<f32>) {
+        self.drift += dv;
+    }
+}
+
+impl LoadActor for Player {
+    const TYPE_NAME: &'static str = "player";
+
+    fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(Self {
+            pos: r.read_finite_point()?,
+            dim: r.read_finite_vector()?,
+            vel: r.read_finite_vector()?,
+            drift: r.read_finite_vector()?,
+        })
+    }
 }
 
 impl Actor for Player {
+    fn type_name(&self) -> &'static str {
+        Self::TYPE_NAME
+    }
+
     #[inline]
     fn get_pos(&self) -> Point2<f32> {
         self.pos
@@ -38,6 +75,13 @@ impl Actor for Player {
         self.vel
     }
 
+    fn shape(&self) -> Shape {
+        Shape::Circle {
+            center: self.pos,
+            radius: HITBOX_RADIUS,
+        }
+    }
+
     #[inline]
     fn set_pos(&mut self, pos: Point2<f32>) {
         self.pos = pos
@@ -53,6 +97,18 @@ impl Actor for Player {
         self.vel = vel
     }
 
+    fn write_state(&self, w: &mut StateWriter) {
+        w.write_point(self.pos);
+        w.write_vector(self.dim);
+        w.write_vector(self.vel);
+        w.write_vector(self.drift);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.add_pos(self.vel * dt);
+        self.drift *= (-DRIFT_DRAG * dt).exp();
+    }
+
     fn draw(
         &self,
         ctx: &mut ggez::Context,
@@ -0,0 +1,179 @@
+use rayon::prelude::*;
+
+use super::*;
+use crate::lifetime::Life;
+
+/// The enemies in play, each with a stable `ActorId`, its tags and its
+/// `Life`.
+///
+/// Ids are handed out in increasing order and never reused, so the entries
+/// stay sorted by id and lookups are a binary search.
+pub struct ActorSet {
+    actors: Vec<Box<dyn Actor>>,
+    ids: Vec<ActorId>,
+    tags: Vec<Vec<String>>,
+    lives: Vec<Life>,
+    next_id: u64,
+}
+
+impl Default for ActorSet {
+    fn default() -> Self {
+        Self {
+            actors: vec![],
+            ids: vec![],
+            tags: vec![],
+            lives: vec![],
+            next_id: ActorId::PLAYER.0 + 1,
+        }
+    }
+}
+
+impl ActorSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the enemy in `spawn`, tagged with its own `Actor::tags` and
+    /// those it came with, and returns its new id.
+    pub fn insert(&mut self, spawn: Spawn) -> ActorId {
+        let id = ActorId(self.next_id);
+        self.next_id += 1;
+        let mut tags: Vec<String> = spawn.actor.tags()
+            .iter()
+            .map(|&tag| tag.to_owned())
+            .collect();
+        for tag in spawn.tags {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        self.actors.push(spawn.actor);
+        self.ids.push(id);
+        self.tags.push(tags);
+        self.lives.push(Life::new(spawn.expiry));
+        id
+    }
+
+    /// Index of the actor with `id` in the slices below, if it still exists.
+    pub fn index_of(&self, id: ActorId) -> Option<usize> {
+        self.ids.binary_search(&id).ok()
+    }
+
+    pub fn get(&self, id: ActorId) -> Option<&dyn Actor> {
+        self.index_of(id).map(|i| self.actors[i].as_ref())
+    }
+
+    pub fn life(&self, id: ActorId) -> Option<&Life> {
+        self.index_of(id).map(|i| &self.lives[i])
+    }
+
+    pub fn get_mut(&mut self, id: ActorId) -> Option<&mut (dyn Actor + 'static)> {
+        match self.index_of(id) {
+            Some(i) => Some(self.actors[i].as_mut()),
+            None => None,
+        }
+    }
+
+    #[inline]
+    pub fn actors(&self) -> &[Box<dyn Actor>] {
+        &self.actors
+    }
+
+    #[inline]
+    pub fn ids(&self) -> &[ActorId] {
+        &self.ids
+    }
+
+    #[inline]
+    pub fn lives(&self) -> &[Life] {
+        &self.lives
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ActorId, &dyn Actor)> {
+        self.ids.iter().cloned().zip(self.actors.iter().map(|a| a.as_ref()))
+    }
+
+    /// Tags of the actor with `id`, none if it is gone.
+    pub fn tags(&self, id: ActorId) -> &[String] {
+        match self.index_of(id) {
+            Some(i) => &self.tags[i],
+            None => &[],
+        }
+    }
+
+    pub fn has_tag(&self, id: ActorId, tag: &str) -> bool {
+        self.tags(id).iter().any(|t| t == tag)
+    }
+
+    /// Actors that were tagged with `tag` when they were inserted.
+    pub fn with_tag<'a>(
+        &'a self,
+        tag: &'a str,
+    ) -> impl Iterator<Item = (ActorId, &'a dyn Actor)> + 'a {
+        self.iter()
+            .zip(self.tags.iter())
+            .filter(move |(_, tags)| tags.iter().any(|t| t == tag))
+            .map(|(entry, _)| entry)
+    }
+
+    /// Every actor along with its life, for updating in parallel.
+    pub fn par_iter_mut(
+        &mut self,
+    ) -> impl IndexedParallelIterator<Item = (&mut Box<dyn Actor>, &mut Life)> {
+        self.actors.par_iter_mut().zip(self.lives.par_iter_mut())
+    }
+
+    /// Ends the life of the actor with `id`, leaving it to fade out.
+    pub fn expire(&mut self, id: ActorId) {
+        if let Some(i) = self.index_of(id) {
+            self.lives[i].expire();
+        }
+    }
+
+    /// Removes every actor whose entry in `keep` is `false`.
+    pub fn retain(&mut self, keep: &[bool]) {
+        let mut keep_iter = keep.iter();
+        self.actors.retain(|_| *keep_iter.next().unwrap());
+        let mut keep_iter = keep.iter();
+        self.ids.retain(|_| *keep_iter.next().unwrap());
+        let mut keep_iter = keep.iter();
+        self.tags.retain(|_| *keep_iter.next().unwrap());
+        let mut keep_iter = keep.iter();
+        self.lives.retain(|_| *keep_iter.next().unwrap());
+    }
+
+    pub fn write_state(&self, w: &mut StateWriter) {
+        w.write_u64(self.next_id);
+        w.write_u32(self.actors.len() as u32);
+        for i in 0..self.actors.len() {
+            w.write_u64(self.ids[i].0);
+            write_actor(self.actors[i].as_ref(), w);
+            w.write_strs(&self.tags[i]);
+            self.lives[i].write_state(w);
+        }
+    }
+
+    pub fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        let next_id = r.read_u64()?;
+        let count = r.read_u32()? as usize;
+        let mut set = Self {
+            actors: Vec::with_capacity(count),
+            ids: Vec::with_capacity(count),
+            tags: Vec::with_capacity(count),
+            lives: Vec::with_capacity(count),
+            next_id,
+        };
+        for _ in 0..count {
+            let id = ActorId(r.read_u64()?);
+            let previous = set.ids.last().cloned().unwrap_or(ActorId::PLAYER);
+            if id <= previous || id.0 >= next_id {
+                return Err(state::invalid(format!("invalid actor id {}", id.0)));
+            }
+            set.ids.push(id);
+            set.actors.push(read_actor(r)?);
+            set.tags.push(r.read_strs()?);
+            set.lives.push(Life::read_state(r)?);
+        }
+        Ok(set)
+    }
+}
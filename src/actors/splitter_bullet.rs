@@ -0,0 +1,330 @@
+use super::*;
+use serde::Deserialize;
+use std::f32::consts::TAU;
+
+use crate::archetype::Archetype;
+
+/// When a `SplitterBullet` breaks apart.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Split {
+    /// This many seconds after it came in.
+    After(f32),
+    /// Once it has flown this many pixels.
+    Distance(f32),
+}
+
+impl Split {
+    pub fn write_state(&self, w: &mut StateWriter) {
+        match *self {
+            Split::After(seconds) => {
+                w.write_u8(0);
+                w.write_f32(seconds);
+            }
+            Split::Distance(distance) => {
+                w.write_u8(1);
+                w.write_f32(distance);
+            }
+        }
+    }
+
+    pub fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(match r.read_u8()? {
+            0 => Split::After(r.read_f32()?),
+            1 => Split::Distance(r.read_f32()?),
+            v => return Err(state::invalid(format!("invalid split {}", v))),
+        })
+    }
+}
+
+/// How a `SplitterBullet` breaks apart.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct Fragments {
+    pub split: Split,
+    /// Pieces it breaks into.
+    pub count: u32,
+    /// Radians the pieces fan out over around its heading. A full turn or
+    /// more spreads them evenly all around.
+    pub spread: f32,
+    /// Generations of smaller splitters before the pieces are bullets of the
+    /// child archetype.
+    pub depth: u32,
+}
+
+impl Fragments {
+    /// Most bullets a single splitter may end up as, over all generations.
+    const MAX_BULLETS: u32 = 256;
+
+    pub fn validate(&self) -> Result<(), String> {
+        let when = match self.split {
+            Split::After(seconds) => seconds,
+            Split::Distance(distance) => distance,
+        };
+        if when <= 0.0 {
+            return Err("must split after a positive time or distance".to_owned());
+        }
+        if self.count == 0 || self.spread < 0.0 {
+            return Err("invalid fragments".to_owned());
+        }
+        let bullets = self.depth.checked_add(1).and_then(|n| self.count.checked_pow(n));
+        if bullets.is_none_or(|bullets| bullets > Self::MAX_BULLETS) {
+            return Err(format!("splits into more than {} bullets", Self::MAX_BULLETS));
+        }
+        Ok(())
+    }
+
+    pub fn write_state(&self, w: &mut StateWriter) {
+        self.split.write_state(w);
+        w.write_u32(self.count);
+        w.write_f32(self.spread);
+        w.write_u32(self.depth);
+    }
+
+    pub fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(Self {
+            split: Split::read_state(r)?,
+            count: r.read_u32()?,
+            spread: r.read_f32()?,
+            depth: r.read_u32()?,
+        })
+    }
+}
+
+/// Flies straight until it breaks apart into `Fragments`.
+pub struct SplitterBullet {
+    pos: Point2<f32>,
+    dim: Vector2<f32>,
+    vel: Vector2<f32>,
+    color: Color,
+    fragments: Fragments,
+    /// What the last generation breaks into. Never an emitter, laser or
+    /// splitter.
+    child: Archetype,
+    /// Its own expiry and tags, passed on to the smaller splitters it breaks
+    /// into.
+    expiry: Expiry,
+    tags: Vec<String>,
+    age: f32,
+    distance: f32,
+}
+
+impl SplitterBullet {
+    /// Size of each generation of fragments relative to its parent.
+    const SHRINK: f32 = 0.7;
+
+    pub fn new(
+        pos: Point2<f32>,
+        dim: Vector2<f32>,
+        vel: Vector2<f32>,
+        color: Color,
+        fragments: Fragments,
+        child: Archetype,
+    ) -> Self {
+        Self {
+            pos,dim,vel,color,fragments,child,
+            expiry: Expiry::default(),
+            tags: vec![],
+            age: 0.0,
+            distance: 0.0,
+        }
+    }
+
+    /// Has the smaller splitters it breaks into spawn with `expiry` and
+    /// `tags`, as it did.
+    pub fn handing_down(mut self, expiry: Expiry, tags: Vec<String>) -> Self {
+        self.expiry = expiry;
+        self.tags = tags;
+        self
+    }
+
+    fn is_due(&self) -> bool {
+        match self.fragments.split {
+            Split::After(seconds) => self.age >= seconds,
+            Split::Distance(distance) => self.distance >= distance,
+        }
+    }
+
+    /// Directions of the fragments, in radians.
+    fn headings(&self) -> Vec<f32> {
+        let heading = self.vel.y.atan2(self.vel.x);
+        let Fragments { count: n, spread, .. } = self.fragments;
+        if spread >= TAU {
+            (0..n).map(|i| heading + TAU * i as f32 / n as f32).collect()
+        }
+        else if n > 1 {
+            let step = spread / (n - 1) as f32;
+            (0..n).map(|i| heading - spread / 2.0 + step * i as f32).collect()
+        }
+        else {
+            vec![heading]
+        }
+    }
+
+    fn fragment(
+        &self,
+        angle: f32,
+        target: Point2<f32>,
+        rng: &mut GameRng,
+    ) -> Option<Spawn> {
+        let dir = Vector2::new(angle.cos(), angle.sin());
+        if self.fragments.depth > 0 {
+            let splitter = SplitterBullet::new(
+                self.pos,
+                self.dim * Self::SHRINK,
+                dir * self.vel.norm(),
+                self.color,
+                Fragments {
+                    depth: self.fragments.depth - 1,
+                    ..self.fragments
+                },
+                self.child.clone(),
+            ).handing_down(self.expiry, self.tags.clone());
+            return Some(Spawn {
+                actor: Box::new(splitter),
+                expiry: self.expiry,
+                tags: self.tags.clone(),
+            });
+        }
+        let (size, speed) = self.child.roll(rng);
+        self.child.bullet(self.pos, size, dir * speed, target)
+    }
+}
+
+impl LoadActor for SplitterBullet {
+    const TYPE_NAME: &'static str = "splitter_bullet";
+
+    fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        let splitter = Self {
+            pos: r.read_finite_point()?,
+            dim: r.read_finite_vector()?,
+            vel: r.read_finite_vector()?,
+            color: r.read_color()?,
+            fragments: Fragments::read_state(r)?,
+            child: Archetype::read_state(r)?,
+            expiry: Expiry::read_state(r)?,
+            tags: r.read_strs()?,
+            age: r.read_f32()?,
+            distance: r.read_f32()?,
+        };
+        splitter.fragments.validate().map_err(state::invalid)?;
+        if !splitter.child.can_be_fired() {
+            return Err(state::invalid(format!("splitter splits into {}", splitter.child.name)));
+        }
+        Ok(splitter)
+    }
+}
+
+impl Actor for SplitterBullet {
+    fn type_name(&self) -> &'static str {
+        Self::TYPE_NAME
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        &["hazard", "bullet", "splitter"]
+    }
+
+    #[inline]
+    fn get_pos(&self) -> Point2<f32> {
+        self.pos
+    }
+
+    #[inline]
+    fn get_rect(&self) -> Rect {
+        Rect{
+            x: self.pos.x - self.dim.x / 2.0,
+            y: self.pos.y - self.dim.y / 2.0,
+            w: self.dim.x,
+            h: self.dim.y,
+        }
+    }
+
+    #[inline]
+    fn get_vel(&self) -> Vector2<f32> {
+        self.vel
+    }
+
+    #[inline]
+    fn set_pos(&mut self, pos: Point2<f32>) {
+        self.pos = pos
+    }
+
+    #[inline]
+    fn set_dim(&mut self, dim: Vector2<f32>) {
+        self.dim = dim
+    }
+
+    #[inline]
+    fn set_vel(&mut self, vel: Vector2<f32>) {
+        self.vel = vel
+    }
+
+    fn write_state(&self, w: &mut StateWriter) {
+        w.write_point(self.pos);
+        w.write_vector(self.dim);
+        w.write_vector(self.vel);
+        w.write_color(self.color);
+        self.fragments.write_state(w);
+        self.child.write_state(w);
+        self.expiry.write_state(w);
+        w.write_strs(&self.tags);
+        w.write_f32(self.age);
+        w.write_f32(self.distance);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.age += dt;
+        self.distance += self.vel.norm() * dt;
+        self.add_pos(self.vel * dt);
+    }
+
+    fn has_action(&self) -> bool { true }
+    fn sense(
+        &self,
+        _: ActorId,
+        world: &World,
+        rng: &mut GameRng,
+    ) -> Action {
+        if !self.is_due() {
+            return Action::default();
+        }
+        let target = world.player.get_pos();
+        let mut commands = vec![Command::DespawnSelf];
+        for angle in self.headings() {
+            commands.extend(self.fragment(angle, target, rng).map(Command::Spawn));
+        }
+        Action {
+            intent: None,
+            commands,
+        }
+    }
+
+    fn draw(
+        &self,
+        ctx: &mut ggez::Context,
+        mesh_builder: Option<&mut MeshBuilder>
+    ) -> ggez::GameResult
+    {
+        let rect = self.get_rect();
+        if let Some(mesh_builder) = mesh_builder {
+            mesh_builder.rectangle(
+                DrawMode::fill(),
+                rect,
+                self.color,
+            );
+        }
+        else {
+            let mesh = Mesh::new_rectangle(
+                ctx,
+                DrawMode::fill(),
+                rect,
+                self.color,
+            )?;
+            ggez::graphics::draw(
+                ctx,
+                &mesh,
+                DrawParam::default(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,678 @@
+use ggez::{GameError, GameResult};
+use ggez::graphics::Color;
+use ggez::nalgebra::{Point2, Vector2};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::actors::*;
+use crate::arena::Arena;
+use crate::lifetime::Expiry;
+use crate::rng::GameRng;
+use crate::state::{self, StateReader, StateWriter};
+
+/// A side of the arena, where enemies come in from.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Edge {
+    pub const ALL: [Edge; 4] = [Edge::Top, Edge::Bottom, Edge::Left, Edge::Right];
+
+    /// The point `along` the edge, from 0 at its top or left end to 1 at the
+    /// other, `inset` pixels into the arena.
+    pub fn point(self, arena: &Arena, along: f32, inset: f32) -> Point2<f32> {
+        match self {
+            Edge::Top => Point2::new(arena.width * along, inset),
+            Edge::Bottom => Point2::new(arena.width * along, arena.height - inset),
+            Edge::Left => Point2::new(inset, arena.height * along),
+            Edge::Right => Point2::new(arena.width - inset, arena.height * along),
+        }
+    }
+
+    /// Direction along the edge, towards its far end.
+    pub fn tangent(self) -> Vector2<f32> {
+        match self {
+            Edge::Top | Edge::Bottom => Vector2::new(1.0, 0.0),
+            Edge::Left | Edge::Right => Vector2::new(0.0, 1.0),
+        }
+    }
+}
+
+/// How an archetype's actors move and act, with the numbers to tune it.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub enum Behavior {
+    /// Flies in a straight line.
+    Straight,
+    /// Sways from side to side, up to `drunk_factor` pixels per second.
+    Drunk { drunk_factor: f32 },
+    /// Steers towards the player by `homing_factor` pixels per second
+    /// squared, never going faster than `speed_limit`, with an `escort` of
+    /// orbiters if it has one.
+    Homing {
+        homing_factor: f32,
+        speed_limit: f32,
+        #[serde(default)]
+        escort: Option<Escort>,
+    },
+    /// Flies in a straight line, reflecting off the edges of the arena
+    /// `bounces` times before going by `then`.
+    Bouncing { bounces: u32, then: AfterBounces },
+    /// Slides along an edge of the arena, firing a volley of the `shot`
+    /// archetype in `pattern` every `interval` seconds.
+    Emitter { pattern: Pattern, interval: f32, shot: String },
+    /// A beam across the arena through the player, harmless while it flashes
+    /// for `warning` seconds, then lethal for `duration` seconds.
+    Laser { warning: f32, duration: f32 },
+    /// Flies in a straight line until it breaks apart into `fragments`,
+    /// ending up as bullets of the `child` archetype.
+    Splitter { fragments: Fragments, child: String },
+    /// Sits still and arms after `arm_time` seconds, then explodes once the
+    /// player comes within `trigger_radius`, in a blast that grows to
+    /// `blast_radius` over `blast_time` seconds and clears any bullets it
+    /// reaches.
+    Mine {
+        arm_time: f32,
+        trigger_radius: f32,
+        blast_radius: f32,
+        blast_time: f32,
+    },
+    /// Drifts across the arena, pulling bullets within `radius` towards
+    /// itself by up to `strength` pixels per second squared, or pushing them
+    /// away if it is negative. Pulls the player too if `pulls_player` is set.
+    GravityWell { radius: f32, strength: f32, pulls_player: bool },
+    /// Closes in on, or moves out to, a circle of `radius` around whatever
+    /// sent it, going around it at `angular_speed` radians per second, and
+    /// flies off once that is gone. Sent in by the spawner, it circles the
+    /// player's position at the time.
+    Orbiter { radius: f32, angular_speed: f32 },
+}
+
+/// Orbiters that come in circling a homing bullet.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Escort {
+    /// Name of an archetype with the `Orbiter` behavior.
+    pub orbiter: String,
+    pub count: u32,
+}
+
+/// A kind of enemy, as listed in `resources/archetypes.ron`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Archetype {
+    pub name: String,
+    pub behavior: Behavior,
+    /// RGBA, from 0 to 1.
+    pub color: [f32; 4],
+    /// Smallest and largest width, in pixels.
+    pub size: (f32, f32),
+    /// Slowest and fastest speed, in pixels per second.
+    pub speed: (f32, f32),
+    /// Chance of being picked by the spawner, relative to the other
+    /// archetypes. Archetypes with 0 can still be fired by emitters.
+    pub weight: u32,
+    /// When its actors go, if not by leaving the arena.
+    #[serde(default)]
+    pub expiry: Expiry,
+    /// Extra tags for its actors, on top of the ones they get for what they
+    /// are, as in `Actor::tags`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Archetype {
+    #[inline]
+    pub fn color(&self) -> Color {
+        self.color.into()
+    }
+
+    /// Whether emitters can fire it, see `bullet`.
+    pub fn can_be_fired(&self) -> bool {
+        match self.behavior {
+            Behavior::Straight
+            | Behavior::Drunk { .. }
+            | Behavior::Homing { escort: None, .. }
+            | Behavior::Bouncing { .. }
+            | Behavior::Mine { .. }
+            | Behavior::GravityWell { .. }
+            | Behavior::Orbiter { .. } => true,
+            Behavior::Emitter { .. }
+            | Behavior::Laser { .. }
+            | Behavior::Splitter { .. }
+            | Behavior::Homing { escort: Some(_), .. } => false,
+        }
+    }
+
+    #[inline]
+    pub fn is_orbiter(&self) -> bool {
+        matches!(self.behavior, Behavior::Orbiter { .. })
+    }
+
+    /// `actor` with the archetype's expiry and tags.
+    pub fn spawn(&self, actor: Box<dyn Actor>) -> Spawn {
+        Spawn {
+            actor,
+            expiry: self.expiry,
+            tags: self.tags.clone(),
+        }
+    }
+
+    /// Picks a size and a speed in the archetype's ranges.
+    pub fn roll(&self, rng: &mut GameRng) -> (f32, f32) {
+        (roll_range(rng, self.size), roll_range(rng, self.speed))
+    }
+
+    /// A bullet of this archetype. Homing bullets start out heading for
+    /// `target` and orbiters circle `pos`.
+    ///
+    /// `None` for emitters, splitters and escorted homing bullets, which need
+    /// the rest of their table to know what they fire, break into or bring
+    /// along, and for lasers, which need the arena to reach across. Those
+    /// are only built by `Archetypes::build`.
+    pub fn bullet(
+        &self,
+        pos: Point2<f32>,
+        size: f32,
+        vel: Vector2<f32>,
+        target: Point2<f32>,
+    ) -> Option<Spawn> {
+        let dim = Vector2::new(size, size);
+        let actor: Box<dyn Actor> = match self.behavior {
+            Behavior::Straight => Box::new(Bullet::new(pos, dim, vel, self.color())),
+            Behavior::Drunk { drunk_factor } => Box::new(DrunkBullet::new(
+                pos, dim, vel, self.color(), drunk_factor,
+            )),
+            Behavior::Homing { homing_factor, speed_limit, escort: None } => {
+                Box::new(HomingBullet::new(
+                    pos, dim, vel, target, self.color(), homing_factor, speed_limit,
+                ))
+            }
+            Behavior::Bouncing { bounces, then } => Box::new(BouncingBullet::new(
+                pos, dim, vel, self.color(), bounces, then,
+            )),
+            Behavior::Mine { arm_time, trigger_radius, blast_radius, blast_time } => {
+                Box::new(Mine::new(
+                    pos, dim, self.color(), arm_time, trigger_radius, blast_radius, blast_time,
+                ))
+            }
+            Behavior::GravityWell { radius, strength, pulls_player } => {
+                Box::new(GravityWell::new(
+                    pos, dim, vel, self.color(), radius, strength, pulls_player,
+                ))
+            }
+            Behavior::Orbiter { .. } => {
+                return self.orbiter(Anchor::Point(pos), pos, vel.y.atan2(vel.x), 0.0, size, vel.norm());
+            }
+            Behavior::Emitter { .. }
+            | Behavior::Laser { .. }
+            | Behavior::Splitter { .. }
+            | Behavior::Homing { escort: Some(_), .. } => return None,
+        };
+        Some(self.spawn(actor))
+    }
+
+    /// An orbiter of this archetype around `anchor`, which is at `center`
+    /// now, starting `distance` pixels away from it in the direction of
+    /// `angle`. `None` unless it has the `Orbiter` behavior.
+    pub fn orbiter(
+        &self,
+        anchor: Anchor,
+        center: Point2<f32>,
+        angle: f32,
+        distance: f32,
+        size: f32,
+        speed: f32,
+    ) -> Option<Spawn> {
+        match self.behavior {
+            Behavior::Orbiter { radius, angular_speed } => {
+                Some(self.spawn(Box::new(OrbiterBullet::new(
+                    anchor,
+                    center,
+                    angle,
+                    distance,
+                    Vector2::new(size, size),
+                    self.color(),
+                    Orbit { radius, angular_speed, speed },
+                ))))
+            }
+            Behavior::Straight
+            | Behavior::Drunk { .. }
+            | Behavior::Homing { .. }
+            | Behavior::Bouncing { .. }
+            | Behavior::Emitter { .. }
+            | Behavior::Laser { .. }
+            | Behavior::Splitter { .. }
+            | Behavior::Mine { .. }
+            | Behavior::GravityWell { .. } => None,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let ordered = |(lo, hi): (f32, f32)| lo >= 0.0 && lo <= hi;
+        if !ordered(self.size) || self.size.0 <= 0.0 {
+            return Err(format!("{}: invalid size range", self.name));
+        }
+        if !ordered(self.speed) {
+            return Err(format!("{}: invalid speed range", self.name));
+        }
+        if let Behavior::Emitter { pattern, interval, .. } = &self.behavior {
+            if *interval <= 0.0 {
+                return Err(format!("{}: interval must be positive", self.name));
+            }
+            if pattern.count() == 0 {
+                return Err(format!("{}: pattern fires no bullets", self.name));
+            }
+        }
+        if let Behavior::Laser { warning, duration } = self.behavior {
+            if warning < 0.0 || duration <= 0.0 {
+                return Err(format!("{}: invalid laser timing", self.name));
+            }
+        }
+        if let Behavior::Mine { arm_time, trigger_radius, blast_radius, blast_time } = self.behavior {
+            if arm_time < 0.0 || trigger_radius <= 0.0 || blast_radius <= 0.0 || blast_time <= 0.0 {
+                return Err(format!("{}: invalid mine", self.name));
+            }
+        }
+        if let Behavior::GravityWell { radius, .. } = self.behavior {
+            if radius <= 0.0 {
+                return Err(format!("{}: radius must be positive", self.name));
+            }
+        }
+        if let Behavior::Orbiter { radius, .. } = self.behavior {
+            if radius <= 0.0 || self.speed.0 <= 0.0 {
+                return Err(format!("{}: radius and speed must be positive", self.name));
+            }
+        }
+        if let Behavior::Homing { escort: Some(escort), .. } = &self.behavior {
+            if escort.count == 0 {
+                return Err(format!("{}: escort has no orbiters", self.name));
+            }
+        }
+        if let Behavior::Splitter { fragments, .. } = &self.behavior {
+            fragments.validate().map_err(|e| format!("{}: {}", self.name, e))?;
+        }
+        Ok(())
+    }
+
+    pub fn write_state(&self, w: &mut StateWriter) {
+        w.write_str(&self.name);
+        match &self.behavior {
+            Behavior::Straight => w.write_u8(0),
+            Behavior::Drunk { drunk_factor } => {
+                w.write_u8(1);
+                w.write_f32(*drunk_factor);
+            }
+            Behavior::Homing { homing_factor, speed_limit, escort } => {
+                w.write_u8(2);
+                w.write_f32(*homing_factor);
+                w.write_f32(*speed_limit);
+                match escort {
+                    None => w.write_u8(0),
+                    Some(escort) => {
+                        w.write_u8(1);
+                        w.write_str(&escort.orbiter);
+                        w.write_u32(escort.count);
+                    }
+                }
+            }
+            Behavior::Emitter { pattern, interval, shot } => {
+                w.write_u8(3);
+                pattern.write_state(w);
+                w.write_f32(*interval);
+                w.write_str(shot);
+            }
+            Behavior::Bouncing { bounces, then } => {
+                w.write_u8(4);
+                w.write_u32(*bounces);
+                then.write_state(w);
+            }
+            Behavior::Laser { warning, duration } => {
+                w.write_u8(5);
+                w.write_f32(*warning);
+                w.write_f32(*duration);
+            }
+            Behavior::Splitter { fragments, child } => {
+                w.write_u8(6);
+                fragments.write_state(w);
+                w.write_str(child);
+            }
+            Behavior::Mine { arm_time, trigger_radius, blast_radius, blast_time } => {
+                w.write_u8(7);
+                w.write_f32(*arm_time);
+                w.write_f32(*trigger_radius);
+                w.write_f32(*blast_radius);
+                w.write_f32(*blast_time);
+            }
+            Behavior::GravityWell { radius, strength, pulls_player } => {
+                w.write_u8(8);
+                w.write_f32(*radius);
+                w.write_f32(*strength);
+                w.write_u8(*pulls_player as u8);
+            }
+            Behavior::Orbiter { radius, angular_speed } => {
+                w.write_u8(9);
+                w.write_f32(*radius);
+                w.write_f32(*angular_speed);
+            }
+        }
+        w.write_color(self.color());
+        w.write_f32(self.size.0);
+        w.write_f32(self.size.1);
+        w.write_f32(self.speed.0);
+        w.write_f32(self.speed.1);
+        w.write_u32(self.weight);
+        self.expiry.write_state(w);
+        w.write_strs(&self.tags);
+    }
+
+    pub fn read_state(r: &mut StateReader) -> GameResult<Self> {
+        let name = r.read_str()?.to_owned();
+        let behavior = match r.read_u8()? {
+            0 => Behavior::Straight,
+            1 => Behavior::Drunk { drunk_factor: r.read_f32()? },
+            2 => Behavior::Homing {
+                homing_factor: r.read_f32()?,
+                speed_limit: r.read_f32()?,
+                escort: match r.read_u8()? {
+                    0 => None,
+                    _ => Some(Escort {
+                        orbiter: r.read_str()?.to_owned(),
+                        count: r.read_u32()?,
+                    }),
+                },
+            },
+            3 => Behavior::Emitter {
+                pattern: Pattern::read_state(r)?,
+                interval: r.read_f32()?,
+                shot: r.read_str()?.to_owned(),
+            },
+            4 => Behavior::Bouncing {
+                bounces: r.read_u32()?,
+                then: AfterBounces::read_state(r)?,
+            },
+            5 => Behavior::Laser {
+                warning: r.read_f32()?,
+                duration: r.read_f32()?,
+            },
+            6 => Behavior::Splitter {
+                fragments: Fragments::read_state(r)?,
+                child: r.read_str()?.to_owned(),
+            },
+            7 => Behavior::Mine {
+                arm_time: r.read_f32()?,
+                trigger_radius: r.read_f32()?,
+                blast_radius: r.read_f32()?,
+                blast_time: r.read_f32()?,
+            },
+            8 => Behavior::GravityWell {
+                radius: r.read_f32()?,
+                strength: r.read_f32()?,
+                pulls_player: r.read_u8()? != 0,
+            },
+            9 => Behavior::Orbiter {
+                radius: r.read_f32()?,
+                angular_speed: r.read_f32()?,
+            },
+            v => return Err(state::invalid(format!("invalid behavior {}", v))),
+        };
+        let color = r.read_color()?;
+        let archetype = Self {
+            name,
+            behavior,
+            color: [color.r, color.g, color.b, color.a],
+            size: (r.read_f32()?, r.read_f32()?),
+            speed: (r.read_f32()?, r.read_f32()?),
+            weight: r.read_u32()?,
+            expiry: Expiry::read_state(r)?,
+            tags: r.read_strs()?,
+        };
+        archetype.validate().map_err(state::invalid)?;
+        Ok(archetype)
+    }
+}
+
+/// Every archetype the spawner picks from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Archetypes {
+    archetypes: Vec<Archetype>,
+}
+
+impl Default for Archetypes {
+    /// The archetypes the game was built with.
+    fn default() -> Self {
+        Self::parse(include_str!("../resources/archetypes.ron"))
+            .expect("built-in archetypes are invalid")
+    }
+}
+
+impl Archetypes {
+    const PATH: &'static str = "/archetypes.ron";
+
+    /// Reads `archetypes.ron` from the resource directory, so they can be
+    /// tuned without rebuilding the game. Falls back to the built-in ones if
+    /// there is no such file.
+    pub fn load(ctx: &mut ggez::Context) -> GameResult<Self> {
+        if !ggez::filesystem::exists(ctx, Self::PATH) {
+            return Ok(Self::default());
+        }
+        let mut source = String::new();
+        std::io::Read::read_to_string(
+            &mut ggez::filesystem::open(ctx, Self::PATH)?,
+            &mut source,
+        )?;
+        Self::parse(&source)
+    }
+
+    pub fn parse(source: &str) -> GameResult<Self> {
+        let archetypes: Vec<Archetype> = ron::de::from_str(source)
+            .map_err(|e| invalid(e.to_string()))?;
+        Self::new(archetypes).map_err(invalid)
+    }
+
+    fn new(archetypes: Vec<Archetype>) -> Result<Self, String> {
+        for (i, archetype) in archetypes.iter().enumerate() {
+            archetype.validate()?;
+            if archetypes[..i].iter().any(|a| a.name == archetype.name) {
+                return Err(format!("{}: defined twice", archetype.name));
+            }
+            let shot = match &archetype.behavior {
+                Behavior::Emitter { shot, .. } => Some(shot),
+                Behavior::Splitter { child, .. } => Some(child),
+                _ => None,
+            };
+            if let Some(shot) = shot {
+                match archetypes.iter().find(|a| &a.name == shot) {
+                    Some(shot) if shot.can_be_fired() => (),
+                    Some(shot) => return Err(format!("{}: can't fire {}", archetype.name, shot.name)),
+                    None => return Err(format!("{}: no archetype named {}", archetype.name, shot)),
+                }
+            }
+            if let Behavior::Homing { escort: Some(escort), .. } = &archetype.behavior {
+                match archetypes.iter().find(|a| a.name == escort.orbiter) {
+                    Some(orbiter) if orbiter.is_orbiter() => (),
+                    Some(orbiter) => return Err(format!("{}: {} is not an orbiter", archetype.name, orbiter.name)),
+                    None => return Err(format!("{}: no archetype named {}", archetype.name, escort.orbiter)),
+                }
+            }
+        }
+        if archetypes.iter().all(|a| a.weight == 0) {
+            return Err("no archetype has a spawn weight".to_owned());
+        }
+        Ok(Self { archetypes })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Archetype> {
+        self.archetypes.iter().find(|a| a.name == name)
+    }
+
+    /// Weight of the most common archetype.
+    pub fn max_weight(&self) -> u32 {
+        self.archetypes.iter().map(|a| a.weight).max().unwrap_or(0)
+    }
+
+    /// Picks an archetype, each as likely as its `weight` says. Falls back
+    /// to the weights from the file if none is above 0.
+    pub fn pick_by(
+        &self,
+        rng: &mut GameRng,
+        weight: impl Fn(&Archetype) -> f32,
+    ) -> &Archetype {
+        let mut weights: Vec<f32> = self.archetypes.iter()
+            .map(|archetype| weight(archetype).max(0.0))
+            .collect();
+        let mut total: f32 = weights.iter().sum();
+        if total <= 0.0 {
+            weights = self.archetypes.iter().map(|a| a.weight as f32).collect();
+            total = weights.iter().sum();
+        }
+        let mut roll = rng.gen_range(0.0, total);
+        let mut picked = 0;
+        for (i, weight) in weights.into_iter().enumerate() {
+            if weight <= 0.0 {
+                continue;
+            }
+            // Rounding can leave the roll just past the last weight, so that
+            // one is kept.
+            picked = i;
+            if roll < weight {
+                break;
+            }
+            roll -= weight;
+        }
+        &self.archetypes[picked]
+    }
+
+    /// An enemy of `archetype` at the point `along` `edge`, as in
+    /// `Edge::point`. Bullets head for `target`, emitters slide along the
+    /// edge, lasers cross the arena through `target` and mines are dropped
+    /// somewhere in the outer half of the arena on that side.
+    ///
+    /// `None` if it fires, breaks into or brings along an archetype that is
+    /// not in the table.
+    pub fn build(
+        &self,
+        archetype: &Archetype,
+        rng: &mut GameRng,
+        arena: &Arena,
+        edge: Edge,
+        along: f32,
+        target: Point2<f32>,
+    ) -> Option<Spawn> {
+        let (size, speed) = archetype.roll(rng);
+        let actor: Box<dyn Actor> = match &archetype.behavior {
+            Behavior::Emitter { pattern, interval, shot } => {
+                const INSET: f32 = 20.0;
+                let speed = if rng.gen() { speed } else { -speed };
+                Box::new(Emitter::new(
+                    edge.point(arena, along, INSET),
+                    Vector2::new(size, size),
+                    edge.tangent() * speed,
+                    archetype.color(),
+                    *pattern,
+                    *interval,
+                    self.get(shot)?.clone(),
+                ))
+            }
+            Behavior::Laser { warning, duration } => {
+                let laser = LaserBeam::across(
+                    arena,
+                    edge.point(arena, along, 0.0),
+                    target,
+                    size,
+                    archetype.color(),
+                    *warning,
+                    *duration,
+                );
+                // Gone once it has fired, whatever the file says.
+                return Some(Spawn {
+                    expiry: Expiry {
+                        max_age: Some(warning + duration),
+                        ..archetype.expiry
+                    },
+                    ..archetype.spawn(Box::new(laser))
+                });
+            }
+            Behavior::Mine { .. } => {
+                let depth = match edge {
+                    Edge::Top | Edge::Bottom => arena.height,
+                    Edge::Left | Edge::Right => arena.width,
+                };
+                let inset = rng.gen_range(0.05, 0.5) * depth;
+                return archetype.bullet(edge.point(arena, along, inset), size, Vector2::new(0.0, 0.0), target);
+            }
+            Behavior::Orbiter { .. } => {
+                let from = edge.point(arena, along, 1.0) - target;
+                return archetype.orbiter(
+                    Anchor::Point(target),
+                    target,
+                    from.y.atan2(from.x),
+                    from.norm(),
+                    size,
+                    speed,
+                );
+            }
+            Behavior::Homing { homing_factor, speed_limit, escort: Some(escort) } => {
+                let pos = edge.point(arena, along, 1.0);
+                Box::new(HomingBullet::new(
+                    pos,
+                    Vector2::new(size, size),
+                    (target - pos).normalize() * speed,
+                    target,
+                    archetype.color(),
+                    *homing_factor,
+                    *speed_limit,
+                ).with_escort(
+                    self.get(&escort.orbiter)?.clone(),
+                    escort.count,
+                ))
+            }
+            Behavior::Splitter { fragments, child } => {
+                let pos = edge.point(arena, along, 1.0);
+                Box::new(SplitterBullet::new(
+                    pos,
+                    Vector2::new(size, size),
+                    (target - pos).normalize() * speed,
+                    archetype.color(),
+                    *fragments,
+                    self.get(child)?.clone(),
+                ).handing_down(archetype.expiry, archetype.tags.clone()))
+            }
+            Behavior::Straight
+            | Behavior::Drunk { .. }
+            | Behavior::Homing { escort: None, .. }
+            | Behavior::Bouncing { .. }
+            | Behavior::GravityWell { .. } => {
+                let pos = edge.point(arena, along, 1.0);
+                let vel = (target - pos).normalize() * speed;
+                return archetype.bullet(pos, size, vel, target);
+            }
+        };
+        Some(archetype.spawn(actor))
+    }
+
+    pub fn write_state(&self, w: &mut StateWriter) {
+        w.write_u32(self.archetypes.len() as u32);
+        for archetype in self.archetypes.iter() {
+            archetype.write_state(w);
+        }
+    }
+
+    pub fn read_state(r: &mut StateReader) -> GameResult<Self> {
+        let count = r.read_u32()?;
+        let mut archetypes = vec![];
+        for _ in 0..count {
+            archetypes.push(Archetype::read_state(r)?);
+        }
+        Self::new(archetypes).map_err(state::invalid)
+    }
+}
+
+fn roll_range(rng: &mut GameRng, (lo, hi): (f32, f32)) -> f32 {
+    if lo < hi { rng.gen_range(lo, hi) } else { lo }
+}
+
+fn invalid(reason: String) -> GameError {
+    GameError::ResourceLoadError(format!("Invalid archetypes: {}", reason))
+}
@@ -0,0 +1,247 @@
+use ggez::graphics::Rect;
+use ggez::nalgebra as na;
+use na::{Point2, Vector2};
+
+use crate::actors::Actor;
+use crate::collision::wrap_offsets;
+use crate::state::{self, StateReader, StateWriter};
+
+/// What happens to an actor that moves past the edge of the arena.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// Comes back in on the opposite side.
+    Wrap,
+    /// Reflects off the edge.
+    Bounce,
+    /// Stops at the edge.
+    Clamp,
+    /// Is removed once it is completely outside.
+    Despawn,
+}
+
+impl Boundary {
+    fn to_u8(self) -> u8 {
+        match self {
+            Boundary::Wrap => 0,
+            Boundary::Bounce => 1,
+            Boundary::Clamp => 2,
+            Boundary::Despawn => 3,
+        }
+    }
+
+    fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(Boundary::Wrap),
+            1 => Some(Boundary::Bounce),
+            2 => Some(Boundary::Clamp),
+            3 => Some(Boundary::Despawn),
+            _ => None,
+        }
+    }
+}
+
+/// The playing field: its size, and how its edges treat each class of actor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Arena {
+    pub width: f32,
+    pub height: f32,
+    pub player: Boundary,
+    pub enemies: Boundary,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self::MODES[0].1
+    }
+}
+
+impl Arena {
+    /// The game modes offered on the title screen, the original one first.
+    pub const MODES: [(&'static str, Arena); 3] = [
+        ("Wraparound", Arena {
+            width: 800.0,
+            height: 600.0,
+            player: Boundary::Wrap,
+            enemies: Boundary::Wrap,
+        }),
+        ("Ricochet", Arena {
+            width: 800.0,
+            height: 600.0,
+            player: Boundary::Clamp,
+            enemies: Boundary::Bounce,
+        }),
+        ("Open Field", Arena {
+            width: 800.0,
+            height: 600.0,
+            player: Boundary::Clamp,
+            enemies: Boundary::Despawn,
+        }),
+    ];
+
+    /// Largest width or height a saved arena may have, which keeps the
+    /// spatial grid over it a sensible size.
+    pub const MAX_SIZE: f32 = 8192.0;
+
+    /// Name of the mode this arena belongs to.
+    pub fn name(&self) -> &'static str {
+        Self::MODES.iter()
+            .find(|(_, arena)| arena == self)
+            .map_or("Custom", |(name, _)| name)
+    }
+
+    /// The mode after this one on the title screen.
+    pub fn next_mode(&self) -> Self {
+        let index = Self::MODES.iter()
+            .position(|(_, arena)| arena == self)
+            .map_or(0, |i| (i + 1) % Self::MODES.len());
+        Self::MODES[index].1
+    }
+
+    #[inline]
+    pub fn size(&self) -> Vector2<f32> {
+        Vector2::new(self.width, self.height)
+    }
+
+    #[inline]
+    pub fn rect(&self) -> Rect {
+        Rect::new(0.0, 0.0, self.width, self.height)
+    }
+
+    /// Whether anything can cross over to the opposite edge, so that
+    /// collisions and drawing have to look across the edges too.
+    pub fn wraps(&self) -> bool {
+        self.player == Boundary::Wrap || self.enemies == Boundary::Wrap
+    }
+
+    /// What the edges do to `enemy`: its own `Actor::boundary`, except that
+    /// only an arena that wraps looks across its edges for hits and drawing,
+    /// so nothing else may wrap.
+    pub fn boundary_of(&self, enemy: &dyn Actor) -> Boundary {
+        match enemy.boundary(self) {
+            Boundary::Wrap if !self.wraps() => self.enemies,
+            boundary => boundary,
+        }
+    }
+
+    /// Boundary to look across the edges by when testing `enemy` against
+    /// the player, as either of them showing up on the other side counts.
+    pub fn boundary_with_player(&self, enemy: &dyn Actor) -> Boundary {
+        match self.player {
+            Boundary::Wrap => Boundary::Wrap,
+            _ => self.boundary_of(enemy),
+        }
+    }
+
+    /// Offsets to move `b` by so that it lines up with `a` wherever they
+    /// can touch, see `collision::wrap_offsets`. Only looks across the edges
+    /// if `boundary` is `Wrap`.
+    pub fn offsets(&self, boundary: Boundary, a: Rect, b: Rect) -> Vec<Vector2<f32>> {
+        match boundary {
+            Boundary::Wrap => wrap_offsets(a, b, self.size()),
+            _ => vec![Vector2::new(0.0, 0.0)],
+        }
+    }
+
+    /// The shortest way to cover `diff`, going across the edges if the
+    /// arena wraps.
+    pub fn shortest(&self, diff: Vector2<f32>) -> Vector2<f32> {
+        if !self.wraps() {
+            return diff;
+        }
+        Vector2::new(
+            diff.x - self.width * (diff.x / self.width).round(),
+            diff.y - self.height * (diff.y / self.height).round(),
+        )
+    }
+
+    /// Applies `boundary` to `actor`, which moved this tick from `start`.
+    ///
+    /// Returns how far it moved for swept collision tests, not counting the
+    /// jump of a wrap, or `None` if it left the arena and should be removed.
+    pub fn confine(
+        &self,
+        boundary: Boundary,
+        actor: &mut dyn Actor,
+        start: Point2<f32>,
+    ) -> Option<Vector2<f32>> {
+        let rect = actor.get_rect();
+        match boundary {
+            Boundary::Wrap => {
+                let mut pos = actor.get_pos();
+                let motion = pos - start;
+                pos.x = pos.x.rem_euclid(self.width);
+                pos.y = pos.y.rem_euclid(self.height);
+                actor.set_pos(pos);
+                return Some(motion);
+            }
+            Boundary::Bounce => {
+                let mut vel = actor.get_vel();
+                let mut shift = Vector2::new(0.0, 0.0);
+                if rect.left() < 0.0 {
+                    shift.x = -2.0 * rect.left();
+                    vel.x = vel.x.abs();
+                }
+                else if rect.right() > self.width {
+                    shift.x = -2.0 * (rect.right() - self.width);
+                    vel.x = -vel.x.abs();
+                }
+                if rect.top() < 0.0 {
+                    shift.y = -2.0 * rect.top();
+                    vel.y = vel.y.abs();
+                }
+                else if rect.bottom() > self.height {
+                    shift.y = -2.0 * (rect.bottom() - self.height);
+                    vel.y = -vel.y.abs();
+                }
+                actor.add_pos(shift);
+                if vel != actor.get_vel() {
+                    actor.set_vel(vel);
+                    actor.on_bounce();
+                }
+            }
+            Boundary::Clamp => {
+                actor.add_pos(Vector2::new(
+                    (-rect.left()).max(0.0) - (rect.right() - self.width).max(0.0),
+                    (-rect.top()).max(0.0) - (rect.bottom() - self.height).max(0.0),
+                ));
+            }
+            Boundary::Despawn => {
+                if !rect.overlaps(&self.rect()) {
+                    return None;
+                }
+            }
+        }
+        Some(actor.get_pos() - start)
+    }
+
+    pub fn write_state(&self, w: &mut StateWriter) {
+        w.write_f32(self.width);
+        w.write_f32(self.height);
+        w.write_u8(self.player.to_u8());
+        w.write_u8(self.enemies.to_u8());
+    }
+
+    pub fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        let width = r.read_f32()?;
+        let height = r.read_f32()?;
+        let fits = |size: f32| size > 0.0 && size <= Self::MAX_SIZE;
+        if !fits(width) || !fits(height) {
+            return Err(state::invalid(format!(
+                "arena size {}x{} is not between 0 and {}",
+                width, height, Self::MAX_SIZE,
+            )));
+        }
+        let mut boundary = || {
+            let v = r.read_u8()?;
+            Boundary::from_u8(v).ok_or_else(|| {
+                state::invalid(format!("unknown boundary {}", v))
+            })
+        };
+        Ok(Self {
+            width,
+            height,
+            player: boundary()?,
+            enemies: boundary()?,
+        })
+    }
+}
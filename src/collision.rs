@@ -0,0 +1,513 @@
+use ggez::graphics::Rect;
+use ggez::nalgebra::{Point2, Vector2};
+
+/// Whether `a` and `b` touched at any point during a tick in which they
+/// moved by `a_motion` and `b_motion`, ending up where they are now.
+///
+/// Both are assumed to move in a straight line, so fast or small actors
+/// can't pass through each other between ticks.
+pub fn swept_overlaps(
+    a: Rect,
+    a_motion: Vector2<f32>,
+    b: Rect,
+    b_motion: Vector2<f32>,
+) -> bool {
+    let half_extent = [(a.w + b.w) / 2.0, (a.h + b.h) / 2.0];
+    let motion = b_motion - a_motion;
+    let end = [
+        (b.x + b.w / 2.0) - (a.x + a.w / 2.0),
+        (b.y + b.h / 2.0) - (a.y + a.h / 2.0),
+    ];
+
+    // Offset between the centers is `end - motion * (1 - t)` for t in 0..1.
+    // Find the t range where it's within `half_extent` on both axes.
+    let mut t_min = 0.0f32;
+    let mut t_max = 1.0f32;
+    for axis in 0..2 {
+        let start = end[axis] - motion[axis];
+        let extent = half_extent[axis];
+        if motion[axis] == 0.0 {
+            if start.abs() > extent {
+                return false;
+            }
+            continue;
+        }
+        let t_enter = (-extent - start) / motion[axis];
+        let t_exit = (extent - start) / motion[axis];
+        t_min = t_min.max(t_enter.min(t_exit));
+        t_max = t_max.min(t_enter.max(t_exit));
+        if t_min > t_max {
+            return false;
+        }
+    }
+    true
+}
+
+/// Smallest rect covering `rect` both now and `motion` ago.
+pub fn swept_bounds(rect: Rect, motion: Vector2<f32>) -> Rect {
+    let x = rect.x.min(rect.x - motion.x);
+    let y = rect.y.min(rect.y - motion.y);
+    Rect::new(x, y, rect.w + motion.x.abs(), rect.h + motion.y.abs())
+}
+
+/// Offsets by which `b`, or one of its copies across the edges of an arena
+/// of `size` that wraps around, lines up with `a` closely enough that their
+/// bounds overlap.
+///
+/// Translating `b`'s shape by each offset in turn gives every way it can
+/// touch `a` on the torus.
+pub fn wrap_offsets(a: Rect, b: Rect, size: Vector2<f32>) -> Vec<Vector2<f32>> {
+    let along = |a_start: f32, a_end: f32, b_start: f32, b_end: f32, size: f32| {
+        [-size, 0.0, size]
+            .iter()
+            .cloned()
+            .filter(|&k| b_start + k <= a_end && a_start <= b_end + k)
+            .collect::<Vec<_>>()
+    };
+    let xs = along(a.left(), a.right(), b.left(), b.right(), size.x);
+    let ys = along(a.top(), a.bottom(), b.top(), b.bottom(), size.y);
+    let mut offsets = Vec::with_capacity(xs.len() * ys.len());
+    for &y in ys.iter() {
+        for &x in xs.iter() {
+            offsets.push(Vector2::new(x, y));
+        }
+    }
+    offsets
+}
+
+/// Area an actor can be hit in.
+#[derive(Clone, Copy, Debug)]
+pub enum Shape {
+    Circle {
+        center: Point2<f32>,
+        radius: f32,
+    },
+    Aabb(Rect),
+    /// Box rotated by `angle` radians around its center.
+    Obb {
+        center: Point2<f32>,
+        half_extents: Vector2<f32>,
+        angle: f32,
+    },
+    /// Line segment from `a` to `b`, thickened by `radius`. A radius of zero
+    /// makes it a plain segment.
+    Capsule {
+        a: Point2<f32>,
+        b: Point2<f32>,
+        radius: f32,
+    },
+}
+
+impl Shape {
+    /// Smallest axis-aligned rect containing the shape.
+    pub fn bounds(&self) -> Rect {
+        match *self {
+            Shape::Circle { center, radius } => Rect::new(
+                center.x - radius,
+                center.y - radius,
+                radius * 2.0,
+                radius * 2.0,
+            ),
+            Shape::Aabb(rect) => rect,
+            Shape::Obb { .. } => {
+                let corners = self.core_box().unwrap().corners();
+                let mut min = corners[0];
+                let mut max = corners[0];
+                for corner in corners.iter().skip(1) {
+                    min = Point2::new(min.x.min(corner.x), min.y.min(corner.y));
+                    max = Point2::new(max.x.max(corner.x), max.y.max(corner.y));
+                }
+                Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+            }
+            Shape::Capsule { a, b, radius } => Rect::new(
+                a.x.min(b.x) - radius,
+                a.y.min(b.y) - radius,
+                (a.x - b.x).abs() + radius * 2.0,
+                (a.y - b.y).abs() + radius * 2.0,
+            ),
+        }
+    }
+
+    pub fn translate(&self, offset: Vector2<f32>) -> Shape {
+        match *self {
+            Shape::Circle { center, radius } => Shape::Circle {
+                center: center + offset,
+                radius,
+            },
+            Shape::Aabb(rect) => Shape::Aabb(Rect::new(
+                rect.x + offset.x,
+                rect.y + offset.y,
+                rect.w,
+                rect.h,
+            )),
+            Shape::Obb { center, half_extents, angle } => Shape::Obb {
+                center: center + offset,
+                half_extents,
+                angle,
+            },
+            Shape::Capsule { a, b, radius } => Shape::Capsule {
+                a: a + offset,
+                b: b + offset,
+                radius,
+            },
+        }
+    }
+
+    /// Whether the shapes touch or overlap.
+    pub fn intersects(&self, other: &Shape) -> bool {
+        if let (Shape::Aabb(a), Shape::Aabb(b)) = (self, other) {
+            return a.overlaps(b);
+        }
+        match (self.core_box(), other.core_box()) {
+            (Some(a), Some(b)) => boxes_overlap(&a, &b),
+            (Some(obb), None) => {
+                let (p, q, radius) = other.core_segment().unwrap();
+                segment_box_distance(obb.to_local(p), obb.to_local(q), obb.half)
+                    <= radius
+            }
+            (None, Some(obb)) => {
+                let (p, q, radius) = self.core_segment().unwrap();
+                segment_box_distance(obb.to_local(p), obb.to_local(q), obb.half)
+                    <= radius
+            }
+            (None, None) => {
+                let (p1, q1, r1) = self.core_segment().unwrap();
+                let (p2, q2, r2) = other.core_segment().unwrap();
+                segment_segment_distance(p1, q1, p2, q2) <= r1 + r2
+            }
+        }
+    }
+
+    /// Narrowest width of the shape, bounding how far it may move between
+    /// samples of a swept test.
+    fn thickness(&self) -> f32 {
+        match *self {
+            Shape::Circle { radius, .. } => radius * 2.0,
+            Shape::Aabb(rect) => rect.w.min(rect.h),
+            Shape::Obb { half_extents, .. } => {
+                half_extents.x.min(half_extents.y) * 2.0
+            }
+            Shape::Capsule { radius, .. } => radius * 2.0,
+        }
+    }
+
+    fn core_box(&self) -> Option<OrientedBox> {
+        match *self {
+            Shape::Aabb(rect) => Some(OrientedBox::new(
+                Point2::new(rect.x + rect.w / 2.0, rect.y + rect.h / 2.0),
+                Vector2::new(rect.w / 2.0, rect.h / 2.0),
+                0.0,
+            )),
+            Shape::Obb { center, half_extents, angle } => {
+                Some(OrientedBox::new(center, half_extents, angle))
+            }
+            _ => None,
+        }
+    }
+
+    /// Round shapes as a segment plus a radius around it.
+    fn core_segment(&self) -> Option<(Point2<f32>, Point2<f32>, f32)> {
+        match *self {
+            Shape::Circle { center, radius } => Some((center, center, radius)),
+            Shape::Capsule { a, b, radius } => Some((a, b, radius)),
+            _ => None,
+        }
+    }
+}
+
+/// Like `swept_overlaps`, for any pair of shapes.
+///
+/// Exact when both are `Aabb`s or either is a `Circle`, whose path is a
+/// capsule. Other pairs are sampled finely enough along the path that
+/// neither can skip over the other.
+pub fn swept_intersects(
+    a: &Shape,
+    a_motion: Vector2<f32>,
+    b: &Shape,
+    b_motion: Vector2<f32>,
+) -> bool {
+    const MAX_SAMPLES: usize = 256;
+
+    // Motion of `b` as seen from `a`
+    let motion = b_motion - a_motion;
+    match (a, b) {
+        (Shape::Aabb(a), Shape::Aabb(b)) => {
+            swept_overlaps(*a, a_motion, *b, b_motion)
+        }
+        (_, &Shape::Circle { center, radius }) => a.intersects(&Shape::Capsule {
+            a: center - motion,
+            b: center,
+            radius,
+        }),
+        (&Shape::Circle { center, radius }, _) => b.intersects(&Shape::Capsule {
+            a: center + motion,
+            b: center,
+            radius,
+        }),
+        _ => {
+            let step = (a.thickness().min(b.thickness()) * 0.5).max(0.5);
+            let samples = ((motion.norm() / step).ceil() as usize)
+                .clamp(1, MAX_SAMPLES);
+            (0..=samples).any(|i| {
+                let back = motion * (1.0 - i as f32 / samples as f32);
+                a.intersects(&b.translate(-back))
+            })
+        }
+    }
+}
+
+struct OrientedBox {
+    center: Point2<f32>,
+    half: Vector2<f32>,
+    axis_x: Vector2<f32>,
+    axis_y: Vector2<f32>,
+}
+
+impl OrientedBox {
+    fn new(center: Point2<f32>, half: Vector2<f32>, angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            center,
+            half,
+            axis_x: Vector2::new(cos, sin),
+            axis_y: Vector2::new(-sin, cos),
+        }
+    }
+
+    /// `p` in the box's frame, where it spans `-half..half`.
+    fn to_local(&self, p: Point2<f32>) -> Point2<f32> {
+        let d = p - self.center;
+        Point2::new(d.dot(&self.axis_x), d.dot(&self.axis_y))
+    }
+
+    fn corners(&self) -> [Point2<f32>; 4] {
+        let x = self.axis_x * self.half.x;
+        let y = self.axis_y * self.half.y;
+        [
+            self.center - x - y,
+            self.center + x - y,
+            self.center + x + y,
+            self.center - x + y,
+        ]
+    }
+
+    /// Half the length of the box projected onto `axis`.
+    fn projected_radius(&self, axis: &Vector2<f32>) -> f32 {
+        self.half.x * self.axis_x.dot(axis).abs()
+            + self.half.y * self.axis_y.dot(axis).abs()
+    }
+}
+
+/// Separating axis test.
+fn boxes_overlap(a: &OrientedBox, b: &OrientedBox) -> bool {
+    let between = b.center - a.center;
+    [a.axis_x, a.axis_y, b.axis_x, b.axis_y].iter().all(|axis| {
+        between.dot(axis).abs()
+            <= a.projected_radius(axis) + b.projected_radius(axis)
+    })
+}
+
+/// Distance from a local point to a box spanning `-half..half`.
+fn point_box_distance(p: Point2<f32>, half: Vector2<f32>) -> f32 {
+    let dx = (p.x.abs() - half.x).max(0.0);
+    let dy = (p.y.abs() - half.y).max(0.0);
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// Distance from a local segment to a box spanning `-half..half`.
+fn segment_box_distance(p: Point2<f32>, q: Point2<f32>, half: Vector2<f32>) -> f32 {
+    // Zero if the segment crosses the box, otherwise the closest points are
+    // an endpoint of the segment or lie on one of the box's edges.
+    let d = q - p;
+    let mut t_min = 0.0f32;
+    let mut t_max = 1.0f32;
+    let mut crosses = true;
+    for axis in 0..2 {
+        if d[axis] == 0.0 {
+            if p[axis].abs() > half[axis] {
+                crosses = false;
+                break;
+            }
+            continue;
+        }
+        let t_enter = (-half[axis] - p[axis]) / d[axis];
+        let t_exit = (half[axis] - p[axis]) / d[axis];
+        t_min = t_min.max(t_enter.min(t_exit));
+        t_max = t_max.min(t_enter.max(t_exit));
+        if t_min > t_max {
+            crosses = false;
+            break;
+        }
+    }
+    if crosses {
+        return 0.0;
+    }
+
+    let corners = [
+        Point2::new(-half.x, -half.y),
+        Point2::new(half.x, -half.y),
+        Point2::new(half.x, half.y),
+        Point2::new(-half.x, half.y),
+    ];
+    let mut distance = point_box_distance(p, half).min(point_box_distance(q, half));
+    for i in 0..4 {
+        distance = distance.min(
+            segment_segment_distance(p, q, corners[i], corners[(i + 1) % 4])
+        );
+    }
+    distance
+}
+
+/// Closest distance between segments `p1-q1` and `p2-q2`, either of which
+/// may be a single point.
+fn segment_segment_distance(
+    p1: Point2<f32>,
+    q1: Point2<f32>,
+    p2: Point2<f32>,
+    q2: Point2<f32>,
+) -> f32 {
+    const EPSILON: f32 = 1e-6;
+
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let a = d1.dot(&d1);
+    let e = d2.dot(&d2);
+    let f = d2.dot(&r);
+
+    let (s, t) = if a <= EPSILON && e <= EPSILON {
+        (0.0, 0.0)
+    }
+    else if a <= EPSILON {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    }
+    else {
+        let c = d1.dot(&r);
+        if e <= EPSILON {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        }
+        else {
+            let b = d1.dot(&d2);
+            let denom = a * e - b * b;
+            let s = if denom != 0.0 {
+                ((b * f - c * e) / denom).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let t = (b * s + f) / e;
+            if t < 0.0 {
+                ((-c / a).clamp(0.0, 1.0), 0.0)
+            }
+            else if t > 1.0 {
+                (((b - c) / a).clamp(0.0, 1.0), 1.0)
+            }
+            else {
+                (s, t)
+            }
+        }
+    };
+    ((p1 + d1 * s) - (p2 + d2 * t)).norm()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn still() -> Vector2<f32> {
+        Vector2::new(0.0, 0.0)
+    }
+
+    /// The player's 10px box, centred on the origin.
+    fn player_rect() -> Rect {
+        Rect::new(-5.0, -5.0, 10.0, 10.0)
+    }
+
+    /// A 4px bullet centred on `(x, y)`.
+    fn bullet_rect(x: f32, y: f32) -> Rect {
+        Rect::new(x - 2.0, y - 2.0, 4.0, 4.0)
+    }
+
+    #[test]
+    fn fast_bullet_hits_what_it_passed_through() {
+        // Crossed from x = -100 to x = 100 within the tick.
+        let bullet = bullet_rect(100.0, 0.0);
+        let motion = Vector2::new(200.0, 0.0);
+        assert!(!player_rect().overlaps(&bullet));
+        assert!(swept_overlaps(player_rect(), still(), bullet, motion));
+        // Seen from the bullet, the player moved the other way.
+        assert!(swept_overlaps(bullet, still(), player_rect(), -motion));
+
+        let player = Shape::Circle { center: Point2::new(0.0, 0.0), radius: 4.0 };
+        assert!(swept_intersects(&player, still(), &Shape::Aabb(bullet), motion));
+        let needle = Shape::Obb {
+            center: Point2::new(100.0, 0.0),
+            half_extents: Vector2::new(6.0, 1.0),
+            angle: 0.3,
+        };
+        assert!(swept_intersects(&Shape::Aabb(player_rect()), still(), &needle, motion));
+    }
+
+    #[test]
+    fn grazing_bullet_misses() {
+        // Passes 0.5px above the player's box.
+        let bullet = bullet_rect(100.0, -7.5);
+        let motion = Vector2::new(200.0, 0.0);
+        assert!(!swept_overlaps(player_rect(), still(), bullet, motion));
+        assert!(swept_overlaps(player_rect(), still(), bullet_rect(100.0, -7.0), motion));
+
+        // Passes 0.5px above the player's circle.
+        let player = Shape::Circle { center: Point2::new(0.0, 0.0), radius: 4.0 };
+        let bullet = Shape::Circle { center: Point2::new(100.0, -6.5), radius: 2.0 };
+        assert!(!swept_intersects(&player, still(), &bullet, motion));
+        assert!(!swept_intersects(&bullet, motion, &player, still()));
+        let bullet = bullet.translate(Vector2::new(0.0, 1.0));
+        assert!(swept_intersects(&player, still(), &bullet, motion));
+        assert!(swept_intersects(&bullet, motion, &player, still()));
+    }
+
+    #[test]
+    fn bullet_stopping_short_misses() {
+        // Moved from x = -100 to x = -8, still 1px left of the player.
+        let bullet = bullet_rect(-8.0, 0.0);
+        let motion = Vector2::new(92.0, 0.0);
+        assert!(!swept_overlaps(player_rect(), still(), bullet, motion));
+        let (player, bullet) = (Shape::Aabb(player_rect()), Shape::Aabb(bullet));
+        assert!(!swept_intersects(&player, still(), &bullet, motion));
+    }
+
+    #[test]
+    fn segment_box_distance_is_to_the_nearest_edge_or_corner() {
+        let half = Vector2::new(2.0, 1.0);
+        let distance = |p: (f32, f32), q: (f32, f32)| {
+            segment_box_distance(Point2::new(p.0, p.1), Point2::new(q.0, q.1), half)
+        };
+        // Crossing the box, or ending inside it.
+        assert_eq!(distance((-5.0, 0.0), (5.0, 0.5)), 0.0);
+        assert_eq!(distance((0.0, 0.0), (0.0, 9.0)), 0.0);
+        // Running alongside the bottom edge.
+        assert_eq!(distance((-5.0, 4.0), (5.0, 4.0)), 3.0);
+        // Pointing away from a corner.
+        let d = distance((5.0, 5.0), (9.0, 9.0));
+        assert!((d - 5.0).abs() < 1e-5, "distance {}", d);
+        // Passing a corner diagonally, closest between its endpoints.
+        let d = distance((6.0, 1.0), (2.0, 5.0));
+        assert!((d - 8.0f32.sqrt()).abs() < 1e-5, "distance {}", d);
+    }
+
+    #[test]
+    fn rotated_box_misses_the_corners_of_its_bounds() {
+        // A beam from (0, 0) to (100, 100), 4px wide.
+        let beam = Shape::Obb {
+            center: Point2::new(50.0, 50.0),
+            half_extents: Vector2::new(50.0 * 2.0f32.sqrt(), 2.0),
+            angle: std::f32::consts::FRAC_PI_4,
+        };
+        let corner = Shape::Circle { center: Point2::new(95.0, 5.0), radius: 2.0 };
+        assert!(beam.bounds().contains(Point2::new(95.0, 5.0)));
+        assert!(!beam.intersects(&corner));
+        assert!(beam.intersects(&corner.translate(Vector2::new(-44.0, 44.0))));
+        // Square ends: just past the end of the beam along its axis.
+        let past_end = Shape::Circle { center: Point2::new(103.0, 103.0), radius: 2.0 };
+        assert!(!beam.intersects(&past_end));
+    }
+}
@@ -0,0 +1,174 @@
+use ggez::nalgebra::Point2;
+use rand::Rng;
+
+use crate::actors::Spawn;
+use crate::archetype::{Archetype, Archetypes, Edge};
+use crate::arena::Arena;
+use crate::rng::GameRng;
+use crate::state::{self, StateReader, StateWriter};
+
+/// How endless mode escalates the longer the player survives.
+///
+/// Each pair is the value at the start of the game and at full difficulty,
+/// which is reached after `ramp` seconds and held from then on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Difficulty {
+    /// Seconds until full difficulty.
+    pub ramp: f32,
+    /// Seconds between enemies.
+    pub interval: (f32, f32),
+    /// Multiplier on the speed range of every archetype.
+    pub speed: (f32, f32),
+    /// Multiplier on the size range of every archetype.
+    pub size: (f32, f32),
+    /// How far the spawn weight of every archetype moves towards that of the
+    /// most common one, from 0 for the weights in `archetypes.ron` to 1 for
+    /// all being equally likely. Archetypes with no weight stay unspawned.
+    pub catch_up: (f32, f32),
+}
+
+impl Default for Difficulty {
+    /// The steady pace, which never escalates.
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+impl Difficulty {
+    /// One enemy every 5 seconds however long the player survives, drawn
+    /// from every archetype with the weights, speeds and sizes in
+    /// `archetypes.ron`.
+    pub const CLASSIC: Difficulty = Difficulty {
+        ramp: 1.0,
+        interval: (5.0, 5.0),
+        speed: (1.0, 1.0),
+        size: (1.0, 1.0),
+        catch_up: (0.0, 0.0),
+    };
+
+    /// The presets offered on the title screen, the steady one first.
+    pub const PRESETS: [(&'static str, Difficulty); 5] = [
+        ("Classic", Self::CLASSIC),
+        ("Easy", Difficulty {
+            ramp: 300.0,
+            interval: (6.0, 3.0),
+            speed: (0.8, 1.0),
+            size: (1.0, 1.0),
+            catch_up: (0.0, 0.02),
+        }),
+        ("Normal", Difficulty {
+            ramp: 240.0,
+            interval: (5.0, 1.5),
+            speed: (1.0, 1.3),
+            size: (1.0, 1.2),
+            catch_up: (0.0, 0.05),
+        }),
+        ("Hard", Difficulty {
+            ramp: 180.0,
+            interval: (4.0, 0.8),
+            speed: (1.1, 1.6),
+            size: (1.0, 1.4),
+            catch_up: (0.02, 0.1),
+        }),
+        ("Insane", Difficulty {
+            ramp: 120.0,
+            interval: (2.5, 0.4),
+            speed: (1.3, 2.0),
+            size: (1.1, 1.6),
+            catch_up: (0.05, 0.2),
+        }),
+    ];
+
+    /// Name of the preset this is, if any.
+    pub fn name(&self) -> &'static str {
+        Self::PRESETS.iter()
+            .find(|(_, difficulty)| difficulty == self)
+            .map_or("Custom", |(name, _)| name)
+    }
+
+    /// The preset after this one on the title screen.
+    pub fn next_preset(&self) -> Self {
+        let index = Self::PRESETS.iter()
+            .position(|(_, difficulty)| difficulty == self)
+            .map_or(0, |i| (i + 1) % Self::PRESETS.len());
+        Self::PRESETS[index].1
+    }
+
+    /// How far along the curve the game is `total_time` seconds in, from 0 to
+    /// 1.
+    pub fn progress(&self, total_time: f32) -> f32 {
+        (total_time / self.ramp).clamp(0.0, 1.0)
+    }
+
+    /// Seconds until the next enemy after one came in at `total_time`.
+    pub fn interval(&self, total_time: f32) -> f32 {
+        lerp(self.interval, self.progress(total_time))
+    }
+
+    /// A random enemy for `total_time` seconds in, on a random edge of
+    /// `arena`, as built by `Archetypes::build`.
+    pub fn spawn(
+        &self,
+        total_time: f32,
+        rng: &mut GameRng,
+        archetypes: &Archetypes,
+        arena: &Arena,
+        target: Point2<f32>,
+    ) -> Option<Spawn> {
+        let t = self.progress(total_time);
+        let catch_up = lerp(self.catch_up, t);
+        let top = archetypes.max_weight() as f32;
+        let archetype = archetypes.pick_by(rng, |archetype| {
+            let weight = archetype.weight as f32;
+            if weight > 0.0 { weight + (top - weight) * catch_up } else { 0.0 }
+        });
+        let (speed, size) = (lerp(self.speed, t), lerp(self.size, t));
+        let archetype = Archetype {
+            speed: (archetype.speed.0 * speed, archetype.speed.1 * speed),
+            size: (archetype.size.0 * size, archetype.size.1 * size),
+            ..archetype.clone()
+        };
+        let edge = Edge::ALL[rng.gen_range(0, 4)];
+        let along = rng.gen_range(0.0, 1.0);
+        archetypes.build(&archetype, rng, arena, edge, along, target)
+    }
+
+    pub fn write_state(&self, w: &mut StateWriter) {
+        w.write_f32(self.ramp);
+        for &(start, full) in [self.interval, self.speed, self.size, self.catch_up].iter() {
+            w.write_f32(start);
+            w.write_f32(full);
+        }
+    }
+
+    pub fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        let ramp = r.read_f32()?;
+        let mut pair = || -> ggez::GameResult<(f32, f32)> {
+            Ok((r.read_f32()?, r.read_f32()?))
+        };
+        let difficulty = Self {
+            ramp,
+            interval: pair()?,
+            speed: pair()?,
+            size: pair()?,
+            catch_up: pair()?,
+        };
+        let positive = |(start, full): (f32, f32)| start > 0.0 && full > 0.0;
+        let fraction = |(start, full): (f32, f32)| {
+            (0.0..=1.0).contains(&start) && (0.0..=1.0).contains(&full)
+        };
+        if !(difficulty.ramp > 0.0
+            && positive(difficulty.interval)
+            && positive(difficulty.speed)
+            && positive(difficulty.size)
+            && fraction(difficulty.catch_up))
+        {
+            return Err(state::invalid(format!("invalid difficulty {:?}", difficulty)));
+        }
+        Ok(difficulty)
+    }
+}
+
+fn lerp((start, full): (f32, f32), t: f32) -> f32 {
+    start + (full - start) * t
+}
@@ -0,0 +1,660 @@
+use std::sync::Arc;
+
+use ggez::{GameError, GameResult};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::archetype::{Archetypes, Edge};
+use crate::rng::GameRng;
+use crate::state::{self, StateReader, StateWriter};
+
+/// Which edge a wave comes in from.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Side {
+    /// A random edge, picked anew for every wave.
+    Any,
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Side {
+    pub fn pick(self, rng: &mut GameRng) -> Edge {
+        match self {
+            Side::Any => Edge::ALL[rng.gen_range(0, 4)],
+            Side::Top => Edge::Top,
+            Side::Bottom => Edge::Bottom,
+            Side::Left => Edge::Left,
+            Side::Right => Edge::Right,
+        }
+    }
+}
+
+/// Where along its edge each enemy of a wave comes in.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Formation {
+    /// Each at a random point.
+    Scattered,
+    /// Evenly spaced along the whole edge.
+    Line,
+    /// All at the same random point.
+    Cluster,
+}
+
+/// Enemies sent in at the same time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wave {
+    pub archetype: String,
+    pub count: u32,
+    pub edge: Side,
+    pub formation: Formation,
+}
+
+/// When a level is won.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Goal {
+    /// Stay alive for this many seconds.
+    Survive(f32),
+    /// Outlast the whole timeline and every hazard it sent, which is any
+    /// enemy tagged "hazard".
+    Clear,
+}
+
+/// A step of a level's timeline, as written in its file.
+#[derive(Deserialize)]
+enum Step {
+    /// Waits this many seconds before going on.
+    Wait(f32),
+    Spawn {
+        archetype: String,
+        count: u32,
+        edge: Side,
+        formation: Formation,
+    },
+    /// Runs `steps` `times` times over.
+    Repeat { times: u32, steps: Vec<Step> },
+    /// Runs `steps` over and over until the level ends.
+    Forever(Vec<Step>),
+    /// Runs one of the branches, picked at random by weight.
+    Choose(Vec<Branch>),
+}
+
+#[derive(Deserialize)]
+struct Branch {
+    weight: u32,
+    steps: Vec<Step>,
+}
+
+#[derive(Deserialize)]
+struct LevelFile {
+    name: String,
+    goal: Goal,
+    timeline: Vec<Step>,
+}
+
+/// A level's timeline flattened into a list, so that the place it has
+/// reached is just an index.
+#[derive(Clone, Debug, PartialEq)]
+enum Op {
+    Wait(f32),
+    Spawn(Wave),
+    /// Starts a loop that runs this many times, or forever.
+    Loop(Option<u32>),
+    /// Ends the loop started at this index.
+    EndLoop(usize),
+    /// Jumps to one of the indices, picked by weight.
+    Choose(Vec<(u32, usize)>),
+    Jump(usize),
+}
+
+/// An authored sequence of waves, loaded from `resources/levels/`.
+#[derive(Debug, PartialEq)]
+pub struct Level {
+    name: String,
+    goal: Goal,
+    ops: Vec<Op>,
+}
+
+impl Level {
+    const DIR: &'static str = "/levels";
+
+    /// The levels the game was built with.
+    pub fn builtin() -> Vec<Self> {
+        vec![
+            Self::parse(include_str!("../resources/levels/01_first_steps.ron"))
+                .expect("built-in level is invalid"),
+        ]
+    }
+
+    /// Reads every `.ron` file in the `levels` resource directory, in order of
+    /// their names. Falls back to the built-in levels if there is no such
+    /// directory.
+    pub fn load_all(ctx: &mut ggez::Context) -> GameResult<Vec<Self>> {
+        if !ggez::filesystem::is_dir(ctx, Self::DIR) {
+            return Ok(Self::builtin());
+        }
+        let mut paths: Vec<_> = ggez::filesystem::read_dir(ctx, Self::DIR)?
+            .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+            .collect();
+        paths.sort();
+        let mut levels = vec![];
+        for path in paths {
+            let mut source = String::new();
+            std::io::Read::read_to_string(
+                &mut ggez::filesystem::open(ctx, &path)?,
+                &mut source,
+            )?;
+            levels.push(Self::parse(&source).map_err(|e| match e {
+                GameError::ResourceLoadError(reason) => GameError::ResourceLoadError(
+                    format!("{}: {}", path.display(), reason),
+                ),
+                e => e,
+            })?);
+        }
+        Ok(levels)
+    }
+
+    pub fn parse(source: &str) -> GameResult<Self> {
+        let file: LevelFile = ron::de::from_str(source)
+            .map_err(|e| invalid(e.to_string()))?;
+        let mut ops = vec![];
+        compile(&file.timeline, &mut ops).map_err(invalid)?;
+        let level = Self {
+            name: file.name,
+            goal: file.goal,
+            ops,
+        };
+        level.validate().map_err(invalid)?;
+        Ok(level)
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn goal(&self) -> Goal {
+        self.goal
+    }
+
+    /// Makes sure every archetype the level spawns exists.
+    pub fn check(&self, archetypes: &Archetypes) -> Result<(), String> {
+        for op in self.ops.iter() {
+            if let Op::Spawn(wave) = op {
+                if archetypes.get(&wave.archetype).is_none() {
+                    return Err(format!(
+                        "{}: no archetype named {}",
+                        self.name, wave.archetype,
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Goal::Survive(seconds) = self.goal {
+            if seconds <= 0.0 {
+                return Err("survival time must be positive".to_owned());
+            }
+        }
+        let len = self.ops.len();
+        for (i, op) in self.ops.iter().enumerate() {
+            let valid = match op {
+                Op::Wait(seconds) => *seconds >= 0.0,
+                Op::Spawn(wave) => wave.count > 0,
+                Op::Loop(times) => *times != Some(0),
+                Op::EndLoop(start) => {
+                    *start < i && matches!(self.ops[*start], Op::Loop(_))
+                }
+                Op::Choose(branches) => {
+                    matches!(total_weight(branches), Some(total) if total > 0)
+                        && branches.iter().all(|&(_, target)| i < target && target <= len)
+                }
+                Op::Jump(target) => i < *target && *target <= len,
+            };
+            if !valid {
+                return Err(format!("invalid step {:?}", op));
+            }
+        }
+        for (end, op) in self.ops.iter().enumerate() {
+            if let Op::EndLoop(start) = *op {
+                if !self.always_waits(start + 1, end) {
+                    return Err("a loop must wait a positive time on every pass".to_owned());
+                }
+                if self.goal == Goal::Clear && self.ops[start] == Op::Loop(None) {
+                    return Err("a level with a forever loop can't be cleared".to_owned());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether every way through the ops from `from` up to `to` passes a
+    /// positive wait. Relies on jumps only ever going forwards.
+    fn always_waits(&self, from: usize, to: usize) -> bool {
+        // `waits[i - from]` is whether every way on from `i` does.
+        let mut waits = vec![false; to - from + 1];
+        let after = |waits: &[bool], target: usize| {
+            target <= to && waits[target - from]
+        };
+        for i in (from..to).rev() {
+            waits[i - from] = match &self.ops[i] {
+                Op::Wait(seconds) => *seconds > 0.0 || waits[i + 1 - from],
+                Op::Choose(branches) => branches.iter()
+                    .filter(|&&(weight, _)| weight > 0)
+                    .all(|&(_, target)| after(&waits, target)),
+                Op::Jump(target) => after(&waits, *target),
+                Op::Spawn(_) | Op::Loop(_) | Op::EndLoop(_) => waits[i + 1 - from],
+            };
+        }
+        waits[0]
+    }
+
+    pub fn write_state(&self, w: &mut StateWriter) {
+        w.write_str(&self.name);
+        match self.goal {
+            Goal::Survive(seconds) => {
+                w.write_u8(0);
+                w.write_f32(seconds);
+            }
+            Goal::Clear => w.write_u8(1),
+        }
+        w.write_u32(self.ops.len() as u32);
+        for op in self.ops.iter() {
+            match op {
+                Op::Wait(seconds) => {
+                    w.write_u8(0);
+                    w.write_f32(*seconds);
+                }
+                Op::Spawn(wave) => {
+                    w.write_u8(1);
+                    w.write_str(&wave.archetype);
+                    w.write_u32(wave.count);
+                    w.write_u8(wave.edge as u8);
+                    w.write_u8(wave.formation as u8);
+                }
+                Op::Loop(times) => {
+                    w.write_u8(2);
+                    w.write_u32(times.unwrap_or(0));
+                }
+                Op::EndLoop(start) => {
+                    w.write_u8(3);
+                    w.write_u32(*start as u32);
+                }
+                Op::Choose(branches) => {
+                    w.write_u8(4);
+                    w.write_u32(branches.len() as u32);
+                    for &(weight, target) in branches.iter() {
+                        w.write_u32(weight);
+                        w.write_u32(target as u32);
+                    }
+                }
+                Op::Jump(target) => {
+                    w.write_u8(5);
+                    w.write_u32(*target as u32);
+                }
+            }
+        }
+    }
+
+    pub fn read_state(r: &mut StateReader) -> GameResult<Self> {
+        let name = r.read_str()?.to_owned();
+        let goal = match r.read_u8()? {
+            0 => Goal::Survive(r.read_f32()?),
+            1 => Goal::Clear,
+            v => return Err(state::invalid(format!("invalid goal {}", v))),
+        };
+        let count = r.read_u32()?;
+        let mut ops = vec![];
+        for _ in 0..count {
+            ops.push(match r.read_u8()? {
+                0 => Op::Wait(r.read_f32()?),
+                1 => Op::Spawn(Wave {
+                    archetype: r.read_str()?.to_owned(),
+                    count: r.read_u32()?,
+                    edge: match r.read_u8()? {
+                        0 => Side::Any,
+                        1 => Side::Top,
+                        2 => Side::Bottom,
+                        3 => Side::Left,
+                        4 => Side::Right,
+                        v => return Err(state::invalid(format!("invalid side {}", v))),
+                    },
+                    formation: match r.read_u8()? {
+                        0 => Formation::Scattered,
+                        1 => Formation::Line,
+                        2 => Formation::Cluster,
+                        v => return Err(state::invalid(format!("invalid formation {}", v))),
+                    },
+                }),
+                // A loop can't run zero times, so 0 stands for forever.
+                2 => Op::Loop(match r.read_u32()? {
+                    0 => None,
+                    times => Some(times),
+                }),
+                3 => Op::EndLoop(r.read_u32()? as usize),
+                4 => {
+                    let count = r.read_u32()?;
+                    let mut branches = vec![];
+                    for _ in 0..count {
+                        branches.push((r.read_u32()?, r.read_u32()? as usize));
+                    }
+                    Op::Choose(branches)
+                }
+                5 => Op::Jump(r.read_u32()? as usize),
+                v => return Err(state::invalid(format!("invalid step {}", v))),
+            });
+        }
+        let level = Self { name, goal, ops };
+        level.validate().map_err(state::invalid)?;
+        Ok(level)
+    }
+}
+
+/// Sum of the weights of `branches`, unless it is too big to count.
+fn total_weight(branches: &[(u32, usize)]) -> Option<u32> {
+    branches.iter().try_fold(0u32, |total, &(weight, _)| total.checked_add(weight))
+}
+
+fn compile(steps: &[Step], ops: &mut Vec<Op>) -> Result<(), String> {
+    for step in steps {
+        match step {
+            Step::Wait(seconds) => ops.push(Op::Wait(*seconds)),
+            Step::Spawn { archetype, count, edge, formation } => {
+                ops.push(Op::Spawn(Wave {
+                    archetype: archetype.clone(),
+                    count: *count,
+                    edge: *edge,
+                    formation: *formation,
+                }));
+            }
+            Step::Repeat { times, steps } => {
+                if *times == 0 {
+                    return Err("a loop must repeat at least once".to_owned());
+                }
+                compile_loop(Some(*times), steps, ops)?;
+            }
+            Step::Forever(steps) => compile_loop(None, steps, ops)?,
+            Step::Choose(branches) => {
+                let choose = ops.len();
+                ops.push(Op::Choose(vec![]));
+                let mut targets = vec![];
+                let mut exits = vec![];
+                for branch in branches {
+                    targets.push((branch.weight, ops.len()));
+                    compile(&branch.steps, ops)?;
+                    exits.push(ops.len());
+                    ops.push(Op::Jump(0));
+                }
+                let end = ops.len();
+                for exit in exits {
+                    ops[exit] = Op::Jump(end);
+                }
+                ops[choose] = Op::Choose(targets);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn compile_loop(
+    times: Option<u32>,
+    steps: &[Step],
+    ops: &mut Vec<Op>,
+) -> Result<(), String> {
+    let start = ops.len();
+    ops.push(Op::Loop(times));
+    compile(steps, ops)?;
+    ops.push(Op::EndLoop(start));
+    Ok(())
+}
+
+/// How far a game has got through its `Level`.
+#[derive(Clone)]
+pub struct LevelRun {
+    level: Arc<Level>,
+    /// Index of the next op.
+    next: usize,
+    /// Seconds until the next op runs.
+    wait: f32,
+    /// Runs left of each loop it is inside, innermost last.
+    loops: Vec<Option<u32>>,
+}
+
+impl LevelRun {
+    /// Most ops run in a single tick, so that even a long stretch without
+    /// waits can't hang the game.
+    const MAX_OPS_PER_TICK: usize = 1000;
+
+    pub fn new(level: Arc<Level>) -> Self {
+        Self {
+            level,
+            next: 0,
+            wait: 0.0,
+            loops: vec![],
+        }
+    }
+
+    #[inline]
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+
+    /// Plays `dt` seconds of the timeline, returning the waves that are due.
+    pub fn advance(&mut self, dt: f32, rng: &mut GameRng) -> Vec<Wave> {
+        let mut waves = vec![];
+        self.wait -= dt;
+        for _ in 0..Self::MAX_OPS_PER_TICK {
+            if self.wait > 0.0 {
+                break;
+            }
+            let op = match self.level.ops.get(self.next) {
+                Some(op) => op,
+                None => break,
+            };
+            self.next += 1;
+            match op {
+                Op::Wait(seconds) => self.wait += seconds,
+                Op::Spawn(wave) => waves.push(wave.clone()),
+                Op::Loop(times) => self.loops.push(*times),
+                Op::EndLoop(start) => match self.loops.last_mut() {
+                    Some(None) => self.next = start + 1,
+                    Some(Some(left)) if *left > 1 => {
+                        *left -= 1;
+                        self.next = start + 1;
+                    }
+                    _ => {
+                        self.loops.pop();
+                    }
+                },
+                Op::Choose(branches) => {
+                    // Validation rules out the rest.
+                    let total = match total_weight(branches) {
+                        Some(total) if total > 0 => total,
+                        _ => continue,
+                    };
+                    let mut roll = rng.gen_range(0, total);
+                    for &(weight, target) in branches.iter() {
+                        if roll < weight {
+                            self.next = target;
+                            break;
+                        }
+                        roll -= weight;
+                    }
+                }
+                Op::Jump(target) => self.next = *target,
+            }
+        }
+        waves
+    }
+
+    /// Whether the whole timeline has played out.
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.level.ops.len() && self.wait <= 0.0
+    }
+
+    pub fn write_state(&self, w: &mut StateWriter) {
+        self.level.write_state(w);
+        self.write_progress(w);
+    }
+
+    /// Everything but the level itself, which never changes during a run.
+    pub fn write_progress(&self, w: &mut StateWriter) {
+        w.write_u32(self.next as u32);
+        w.write_f32(self.wait);
+        w.write_u32(self.loops.len() as u32);
+        for times in self.loops.iter() {
+            w.write_u32(times.unwrap_or(0));
+        }
+    }
+
+    pub fn read_state(r: &mut StateReader) -> GameResult<Self> {
+        let level = Level::read_state(r)?;
+        Self::read_run(Arc::new(level), r)
+    }
+
+    /// Reads what `write_progress` wrote for a run of the same level.
+    pub fn read_progress(&self, r: &mut StateReader) -> GameResult<Self> {
+        Self::read_run(self.level.clone(), r)
+    }
+
+    fn read_run(level: Arc<Level>, r: &mut StateReader) -> GameResult<Self> {
+        let next = r.read_u32()? as usize;
+        if next > level.ops.len() {
+            return Err(state::invalid(format!("invalid step index {}", next)));
+        }
+        let wait = r.read_f32()?;
+        let count = r.read_u32()?;
+        let mut loops = vec![];
+        for _ in 0..count {
+            loops.push(match r.read_u32()? {
+                0 => None,
+                times => Some(times),
+            });
+        }
+        Ok(Self {
+            level,
+            next,
+            wait,
+            loops,
+        })
+    }
+}
+
+fn invalid(reason: String) -> GameError {
+    GameError::ResourceLoadError(format!("Invalid level: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    /// A level with `timeline`, written the way level files are.
+    fn level(goal: &str, timeline: &str) -> GameResult<Level> {
+        Level::parse(&format!(
+            "(name: \"Test\", goal: {}, timeline: [{}])",
+            goal, timeline,
+        ))
+    }
+
+    fn spawn(archetype: &str, count: u32) -> String {
+        format!(
+            "Spawn(archetype: \"{}\", count: {}, edge: Top, formation: Line)",
+            archetype, count,
+        )
+    }
+
+    /// Plays `run` in quarter seconds until it finishes, returning the tick
+    /// each wave came on.
+    fn play(run: &mut LevelRun, max_ticks: u32) -> Vec<(u32, String, u32)> {
+        let mut rng = GameRng::seed_from_u64(1);
+        let mut waves = vec![];
+        for tick in 1..=max_ticks {
+            for wave in run.advance(0.25, &mut rng) {
+                waves.push((tick, wave.archetype, wave.count));
+            }
+            if run.is_finished() {
+                break;
+            }
+        }
+        waves
+    }
+
+    #[test]
+    fn builtin_levels_are_valid() {
+        assert!(!Level::builtin().is_empty());
+    }
+
+    #[test]
+    fn plays_out_waits_spawns_and_loops() {
+        let level = level("Clear", &format!(
+            "Wait(1.0), {}, Repeat(times: 2, steps: [Wait(0.5), {}])",
+            spawn("a", 2), spawn("b", 1),
+        )).unwrap();
+        let mut run = LevelRun::new(Arc::new(level));
+        assert_eq!(play(&mut run, 100), vec![
+            (4, "a".to_owned(), 2),
+            (6, "b".to_owned(), 1),
+            (8, "b".to_owned(), 1),
+        ]);
+        assert!(run.is_finished());
+    }
+
+    #[test]
+    fn forever_loops_never_finish() {
+        let level = level("Survive(10.0)", &format!(
+            "Forever([{}, Wait(1.0)])",
+            spawn("a", 1),
+        )).unwrap();
+        let mut run = LevelRun::new(Arc::new(level));
+        let ticks: Vec<_> = play(&mut run, 40).into_iter().map(|(tick, ..)| tick).collect();
+        assert_eq!(ticks, vec![1, 4, 8, 12, 16, 20, 24, 28, 32, 36, 40]);
+        assert!(!run.is_finished());
+    }
+
+    #[test]
+    fn chooses_only_branches_with_weight() {
+        let level = Arc::new(level("Clear", &format!(
+            "Choose([(weight: 0, steps: [{}]), (weight: 3, steps: [{}])])",
+            spawn("never", 1), spawn("always", 1),
+        )).unwrap());
+        for seed in 0..20 {
+            let mut run = LevelRun::new(level.clone());
+            let mut rng = GameRng::seed_from_u64(seed);
+            let waves = run.advance(0.25, &mut rng);
+            assert_eq!(waves.len(), 1);
+            assert_eq!(waves[0].archetype, "always");
+        }
+    }
+
+    #[test]
+    fn rejects_loops_that_can_pass_without_waiting() {
+        let survive = |timeline: String| level("Survive(10.0)", &timeline);
+        let choose = |first: u32, second: u32, steps: String| format!(
+            "Forever([Choose([(weight: {}, steps: [Wait(1.0)]), (weight: {}, steps: [{}])])])",
+            first, second, steps,
+        );
+        assert!(survive(format!("Forever([{}])", spawn("a", 1))).is_err());
+        assert!(survive(format!("Forever([{}, Wait(0.0)])", spawn("a", 1))).is_err());
+        assert!(survive(choose(1, 1, spawn("a", 1))).is_err());
+        // Branches that are never picked don't have to wait.
+        assert!(survive(choose(1, 0, spawn("a", 1))).is_ok());
+        assert!(survive(choose(1, 1, format!("{}, Wait(2.0)", spawn("a", 1)))).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_levels() {
+        let forever = format!("Forever([{}, Wait(1.0)])", spawn("a", 1));
+        assert!(level("Clear", &forever).is_err());
+        assert!(level("Survive(0.0)", "Wait(1.0)").is_err());
+        assert!(level("Clear", "Wait(-1.0)").is_err());
+        assert!(level("Clear", &spawn("a", 0)).is_err());
+        assert!(level("Clear", "Repeat(times: 0, steps: [Wait(1.0)])").is_err());
+        assert!(level("Clear", "Choose([(weight: 0, steps: [])])").is_err());
+        assert!(level("Clear", "Wait(1.0").is_err());
+    }
+}
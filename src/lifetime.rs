@@ -0,0 +1,179 @@
+use ggez::nalgebra::Vector2;
+use serde::Deserialize;
+
+use crate::state::{self, StateReader, StateWriter};
+
+/// Conditions that end an actor's life, whichever is met first.
+///
+/// The default never expires. Set per archetype in `archetypes.ron`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Expiry {
+    /// Seconds since it was spawned.
+    pub max_age: Option<f32>,
+    /// Distance it has moved, not counting the jumps of wrapping around.
+    pub max_distance: Option<f32>,
+    /// How many times it may wrap around an edge of the arena.
+    pub max_wraps: Option<u32>,
+    /// Expire as soon as its center leaves the arena, before the arena's
+    /// boundary gets to wrap, bounce or clamp it.
+    pub on_exit: bool,
+}
+
+impl Expiry {
+    pub fn write_state(&self, w: &mut StateWriter) {
+        match self.max_age {
+            Some(max) => {
+                w.write_u8(1);
+                w.write_f32(max);
+            }
+            None => w.write_u8(0),
+        }
+        match self.max_distance {
+            Some(max) => {
+                w.write_u8(1);
+                w.write_f32(max);
+            }
+            None => w.write_u8(0),
+        }
+        match self.max_wraps {
+            Some(max) => {
+                w.write_u8(1);
+                w.write_u32(max);
+            }
+            None => w.write_u8(0),
+        }
+        w.write_u8(self.on_exit as u8);
+    }
+
+    pub fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(Self {
+            max_age: match r.read_u8()? {
+                0 => None,
+                _ => Some(r.read_f32()?),
+            },
+            max_distance: match r.read_u8()? {
+                0 => None,
+                _ => Some(r.read_f32()?),
+            },
+            max_wraps: match r.read_u8()? {
+                0 => None,
+                _ => Some(r.read_u32()?),
+            },
+            on_exit: r.read_u8()? != 0,
+        })
+    }
+}
+
+/// How far an actor has come towards its `Expiry`.
+#[derive(Clone, Copy, Debug)]
+pub struct Life {
+    expiry: Expiry,
+    age: f32,
+    distance: f32,
+    wraps: u32,
+    /// Seconds of fading out left, once expired.
+    fading: Option<f32>,
+}
+
+impl Life {
+    /// How long an expired actor stays visible, harmless, before it is
+    /// removed.
+    pub const FADE_TIME: f32 = 0.5;
+
+    pub fn new(expiry: Expiry) -> Self {
+        Self {
+            expiry,
+            age: 0.0,
+            distance: 0.0,
+            wraps: 0,
+            fading: None,
+        }
+    }
+
+    #[inline]
+    pub fn expiry(&self) -> &Expiry {
+        &self.expiry
+    }
+
+    /// Accounts for a tick of `dt` seconds in which the actor moved by
+    /// `motion`, and whether it `wrapped` or `exited` the arena.
+    ///
+    /// Returns `false` once the actor has faded out and should be removed.
+    pub fn advance(
+        &mut self,
+        dt: f32,
+        motion: Vector2<f32>,
+        wrapped: bool,
+        exited: bool,
+    ) -> bool {
+        self.age += dt;
+        self.distance += motion.norm();
+        if wrapped {
+            self.wraps += 1;
+        }
+        match &mut self.fading {
+            Some(left) => {
+                *left -= dt;
+                *left > 0.0
+            }
+            None => {
+                let expiry = &self.expiry;
+                let expired = expiry.max_age.is_some_and(|max| self.age >= max)
+                    || expiry.max_distance.is_some_and(|max| self.distance >= max)
+                    || expiry.max_wraps.is_some_and(|max| self.wraps > max)
+                    || expiry.on_exit && exited;
+                if expired {
+                    self.expire();
+                }
+                true
+            }
+        }
+    }
+
+    /// Ends the actor's life now, unless it already ended.
+    pub fn expire(&mut self) {
+        if self.fading.is_none() {
+            self.fading = Some(Self::FADE_TIME);
+        }
+    }
+
+    /// Whether it has expired and is only still around to fade out.
+    #[inline]
+    pub fn is_fading(&self) -> bool {
+        self.fading.is_some()
+    }
+
+    /// Opacity to draw the actor with.
+    pub fn alpha(&self) -> f32 {
+        self.fading.map_or(1.0, |left| (left / Self::FADE_TIME).max(0.0))
+    }
+
+    pub fn write_state(&self, w: &mut StateWriter) {
+        self.expiry.write_state(w);
+        w.write_f32(self.age);
+        w.write_f32(self.distance);
+        w.write_u32(self.wraps);
+        match self.fading {
+            Some(left) => {
+                w.write_u8(1);
+                w.write_f32(left);
+            }
+            None => w.write_u8(0),
+        }
+    }
+
+    pub fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(Self {
+            expiry: Expiry::read_state(r)?,
+            age: r.read_f32()?,
+            distance: r.read_f32()?,
+            wraps: r.read_u32()?,
+            fading: match r.read_u8()? {
+                0 => None,
+                1 => Some(r.read_f32()?),
+                v => return Err(state::invalid(format!("invalid fading flag {}", v))),
+            },
+        })
+    }
+}
@@ -1,6 +1,7 @@
-// #[macro_use] extern crate lazy_static;
+#[macro_use] extern crate lazy_static;
 
 use std::collections::VecDeque;
+use std::sync::Arc;
 
 use ggez;
 use ggez::event;
@@ -9,8 +10,20 @@ use ggez::input::keyboard::{
     KeyCode, KeyMods,
 };
 
-mod scene; use scene::{SceneEvent, SceneBox};
+mod scene; use scene::{SceneEvent, SceneBox, Settings};
 mod actors;
+mod simulation;
+mod rng;
+mod state;
+mod spatial;
+mod collision;
+mod arena;
+mod archetype; use archetype::Archetypes;
+mod difficulty;
+mod level; use level::Level;
+mod spawner;
+mod lifetime;
+mod replay; use replay::Replay;
 
 struct MainState {
     current_scene: Option<SceneBox>,
@@ -18,18 +31,57 @@ struct MainState {
     scene_event_queue: VecDeque<SceneEvent>,
 }
 
+/// What the game opens with, chosen on the command line.
+enum Start {
+    NewGame,
+    Replay(Replay),
+    Snapshot(Vec<u8>),
+}
+
 impl MainState {
-    fn new(ctx: &mut ggez::Context) -> ggez::GameResult<MainState> {
+    fn new(
+        ctx: &mut ggez::Context,
+        mut settings: Settings,
+        start: Start,
+    ) -> ggez::GameResult<MainState> {
+        settings.archetypes = Arc::new(Archetypes::load(ctx)?);
+        let levels = Level::load_all(ctx)?;
+        for level in levels.iter() {
+            level.check(&settings.archetypes).map_err(|reason| {
+                ggez::GameError::ResourceLoadError(format!("Invalid level: {}", reason))
+            })?;
+        }
+        settings.levels = Arc::new(levels.into_iter().map(Arc::new).collect());
         let font = graphics::Font::new_glyph_font_bytes(
             ctx,
             include_bytes!("../resources/Silver.ttf")
         )?;
+        let (gameplay_scene, is_resumed) = match start {
+            Start::NewGame => (
+                scene::GameplayScene::new_box(font, settings.clone()),
+                false,
+            ),
+            Start::Snapshot(snapshot) => (
+                scene::GameplayScene::resume_box(font, settings.clone(), snapshot)?,
+                true,
+            ),
+            Start::Replay(replay) => {
+                let s = Self {
+                    current_scene: Some(
+                        scene::ReplayScene::new_box(font, replay, settings)
+                    ),
+                    previous_scene_stack: vec![],
+                    scene_event_queue: VecDeque::new(),
+                };
+                return Ok(s);
+            }
+        };
         let s = Self {
             current_scene: Some(
-                scene::StartingScene::new_box(font)
+                scene::StartingScene::new_box(font, settings, is_resumed)
             ),
             previous_scene_stack: vec![
-                scene::GameplayScene::new_box(font),
+                gameplay_scene,
             ],
             scene_event_queue: VecDeque::new(),
         };
@@ -142,10 +194,52 @@ impl event::EventHandler for MainState {
     }
 }
 
+fn parse_args() -> ggez::GameResult<(Settings, Start)> {
+    let mut settings = Settings::default();
+    let mut start = Start::NewGame;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                settings.seed = args.next().and_then(|s| s.parse().ok());
+                if settings.seed.is_none() {
+                    eprintln!("--seed expects an unsigned integer, ignoring");
+                }
+            }
+            "--replay" => match args.next() {
+                Some(path) => {
+                    let file = std::fs::File::open(path)?;
+                    start = Start::Replay(
+                        Replay::read(std::io::BufReader::new(file))?
+                    );
+                }
+                None => eprintln!("--replay expects a file path, ignoring"),
+            },
+            "--snapshot" => match args.next() {
+                Some(path) => start = Start::Snapshot(std::fs::read(path)?),
+                None => eprintln!("--snapshot expects a file path, ignoring"),
+            },
+            _ => eprintln!("Unknown argument: {}", arg),
+        }
+    }
+    Ok((settings, start))
+}
+
 pub fn main() -> ggez::GameResult { 
-    let cb = ggez::ContextBuilder::new("LD45", "prixt");
+    let (settings, start) = parse_args()?;
+    let mut cb = ggez::ContextBuilder::new("LD45", "prixt")
+        .window_mode(
+            ggez::conf::WindowMode::default()
+                .dimensions(settings.arena.width, settings.arena.height)
+        );
+    // Lets `cargo run` pick up edits to the files in `resources/`.
+    if let Ok(manifest_dir) = std::env::var("CARGO_MANIFEST_DIR") {
+        let mut path = std::path::PathBuf::from(manifest_dir);
+        path.push("resources");
+        cb = cb.add_resource_path(path);
+    }
     let (ctx, event_loop) = &mut cb.build()?;
-    let state = &mut MainState::new(ctx)?;
+    let state = &mut MainState::new(ctx, settings, start)?;
     graphics::set_window_title(ctx, "LD45 - Dodge Bullet");
     event::run(ctx, event_loop, state)
-}
\ No newline at end of file
+}
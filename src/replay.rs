@@ -0,0 +1,363 @@
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use ggez::{GameError, GameResult};
+
+use crate::archetype::Archetypes;
+use crate::arena::Arena;
+use crate::simulation::{Input, Simulation};
+use crate::spawner::Spawner;
+use crate::state::{StateReader, StateWriter};
+
+/// A recorded run: everything needed to play it back tick by tick.
+///
+/// File layout, all integers little endian:
+/// magic `LDRP`, format version `u16`, game version (`u8` length + UTF-8),
+/// seed `u64`, arena (width and height `f32`, player and enemy boundary
+/// `u8`), archetypes (`u32` length + `Archetypes::write_state`), spawner
+/// (`u32` length + `Spawner::write_state`), starting snapshot (`u32` length + bytes, empty when the run started fresh from the
+/// seed), tick count `u32`, run count `u32`, then `(input u8, length u16)`
+/// runs of identical input, then checksum interval `u32`, checksum count `u32`
+/// and the `u64` checksums.
+#[derive(Clone)]
+pub struct Replay {
+    game_version: String,
+    seed: u64,
+    arena: Arena,
+    archetypes: Arc<Archetypes>,
+    spawner: Spawner,
+    snapshot: Option<Vec<u8>>,
+    inputs: Vec<Input>,
+    checksum_interval: u32,
+    /// `Simulation::checksum` after every `checksum_interval` ticks.
+    checksums: Vec<u64>,
+}
+
+impl Replay {
+    const MAGIC: [u8; 4] = *b"LDRP";
+    const FORMAT_VERSION: u16 = 9;
+    const GAME_VERSION: &'static str = env!("CARGO_PKG_VERSION");
+    const CHECKSUM_INTERVAL: u32 = 60;
+
+    pub fn new(
+        seed: u64,
+        arena: Arena,
+        archetypes: Arc<Archetypes>,
+        spawner: Spawner,
+    ) -> Self {
+        Self {
+            game_version: Self::GAME_VERSION.to_owned(),
+            seed,
+            arena,
+            archetypes,
+            spawner,
+            snapshot: None,
+            inputs: vec![],
+            checksum_interval: Self::CHECKSUM_INTERVAL,
+            checksums: vec![],
+        }
+    }
+
+    /// A replay of a session resumed from `snapshot` rather than started fresh.
+    pub fn from_snapshot(
+        seed: u64,
+        arena: Arena,
+        archetypes: Arc<Archetypes>,
+        spawner: Spawner,
+        snapshot: Vec<u8>,
+    ) -> Self {
+        Self {
+            snapshot: Some(snapshot),
+            ..Self::new(seed, arena, archetypes, spawner)
+        }
+    }
+
+    /// The simulation as it was before the first recorded tick.
+    pub fn start(&self) -> GameResult<Simulation> {
+        match &self.snapshot {
+            Some(snapshot) => Simulation::load_snapshot(snapshot),
+            None => Ok(Simulation::new(
+                self.seed,
+                self.arena,
+                self.archetypes.clone(),
+                self.spawner.clone(),
+            )),
+        }
+    }
+
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    #[inline]
+    pub fn inputs(&self) -> &[Input] {
+        &self.inputs
+    }
+
+    /// Ticks between stored checksums, so how closely `verify` can tell when
+    /// a playback diverged.
+    #[inline]
+    pub fn checksum_interval(&self) -> usize {
+        self.checksum_interval as usize
+    }
+
+    /// Records the `input` of a tick, given the simulation right after it.
+    pub fn record(&mut self, input: Input, simulation: &Simulation) {
+        self.inputs.push(input);
+        let interval = self.checksum_interval as usize;
+        if self.inputs.len() / interval > self.checksums.len() {
+            self.checksums.push(simulation.checksum());
+        }
+    }
+
+    /// Drops everything recorded after the first `ticks` ticks, for when the
+    /// game is rewound.
+    pub fn truncate(&mut self, ticks: usize) {
+        self.inputs.truncate(ticks);
+        self.checksums.truncate(ticks / self.checksum_interval as usize);
+    }
+
+    /// Compares the simulation after `tick` ticks of playback with the
+    /// recording. Returns `false` if they have diverged at some point since
+    /// the previous stored checksum, `checksum_interval` ticks earlier; ticks
+    /// without a stored checksum always pass.
+    pub fn verify(&self, tick: usize, simulation: &Simulation) -> bool {
+        let interval = self.checksum_interval as usize;
+        let index = tick / interval;
+        if index == 0 || index * interval != tick {
+            return true;
+        }
+        match self.checksums.get(index - 1) {
+            Some(&checksum) => checksum == simulation.checksum(),
+            None => true,
+        }
+    }
+
+    pub fn write<W: Write>(&self, mut w: W) -> GameResult {
+        let mut runs: Vec<(u8, u16)> = vec![];
+        for input in self.inputs.iter() {
+            let bits = input.to_bits();
+            match runs.last_mut() {
+                Some((last, len)) if *last == bits && *len < u16::MAX => {
+                    *len += 1;
+                }
+                _ => runs.push((bits, 1)),
+            }
+        }
+
+        w.write_all(&Self::MAGIC)?;
+        w.write_all(&Self::FORMAT_VERSION.to_le_bytes())?;
+        w.write_all(&[self.game_version.len() as u8])?;
+        w.write_all(self.game_version.as_bytes())?;
+        w.write_all(&self.seed.to_le_bytes())?;
+        let mut arena = StateWriter::new();
+        self.arena.write_state(&mut arena);
+        w.write_all(&arena.into_bytes())?;
+        let mut archetypes = StateWriter::new();
+        self.archetypes.write_state(&mut archetypes);
+        let archetypes = archetypes.into_bytes();
+        w.write_all(&(archetypes.len() as u32).to_le_bytes())?;
+        w.write_all(&archetypes)?;
+        let mut spawner = StateWriter::new();
+        self.spawner.write_state(&mut spawner);
+        let spawner = spawner.into_bytes();
+        w.write_all(&(spawner.len() as u32).to_le_bytes())?;
+        w.write_all(&spawner)?;
+        let snapshot = self.snapshot.as_ref().map_or(&[][..], |s| &s[..]);
+        w.write_all(&(snapshot.len() as u32).to_le_bytes())?;
+        w.write_all(snapshot)?;
+        w.write_all(&(self.inputs.len() as u32).to_le_bytes())?;
+        w.write_all(&(runs.len() as u32).to_le_bytes())?;
+        for (bits, len) in runs {
+            w.write_all(&[bits])?;
+            w.write_all(&len.to_le_bytes())?;
+        }
+        w.write_all(&self.checksum_interval.to_le_bytes())?;
+        w.write_all(&(self.checksums.len() as u32).to_le_bytes())?;
+        for checksum in self.checksums.iter() {
+            w.write_all(&checksum.to_le_bytes())?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut r: R) -> GameResult<Self> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if magic != Self::MAGIC {
+            return Err(invalid("not a replay file".to_owned()));
+        }
+        let format_version = read_u16(&mut r)?;
+        if format_version != Self::FORMAT_VERSION {
+            return Err(invalid(format!(
+                "replay format version {} is not supported (expected {})",
+                format_version, Self::FORMAT_VERSION,
+            )));
+        }
+        let mut game_version = vec![0u8; read_u8(&mut r)? as usize];
+        r.read_exact(&mut game_version)?;
+        let game_version = String::from_utf8(game_version)
+            .map_err(|_| invalid("game version is not valid UTF-8".to_owned()))?;
+        if game_version != Self::GAME_VERSION {
+            return Err(invalid(format!(
+                "replay was recorded with version {}, this is version {}",
+                game_version, Self::GAME_VERSION,
+            )));
+        }
+        let seed = read_u64(&mut r)?;
+        let mut arena = [0u8; 10];
+        r.read_exact(&mut arena)?;
+        let arena = Arena::read_state(&mut StateReader::new(&arena))?;
+        let mut archetypes = vec![0u8; read_u32(&mut r)? as usize];
+        r.read_exact(&mut archetypes)?;
+        let archetypes = Arc::new(
+            Archetypes::read_state(&mut StateReader::new(&archetypes))?
+        );
+        let mut spawner = vec![0u8; read_u32(&mut r)? as usize];
+        r.read_exact(&mut spawner)?;
+        let spawner = Spawner::read_state(&mut StateReader::new(&spawner))?;
+        spawner.check(&archetypes).map_err(invalid)?;
+        let snapshot_len = read_u32(&mut r)? as usize;
+        let snapshot = if snapshot_len > 0 {
+            let mut snapshot = vec![0u8; snapshot_len];
+            r.read_exact(&mut snapshot)?;
+            Some(snapshot)
+        }
+        else {
+            None
+        };
+        let tick_count = read_u32(&mut r)? as usize;
+        let run_count = read_u32(&mut r)?;
+
+        let mut inputs = Vec::with_capacity(tick_count);
+        for _ in 0..run_count {
+            let bits = read_u8(&mut r)?;
+            let input = Input::from_bits(bits)
+                .ok_or_else(|| invalid(format!("invalid input bits {:#x}", bits)))?;
+            let len = read_u16(&mut r)?;
+            inputs.resize(inputs.len() + len as usize, input);
+        }
+        if inputs.len() != tick_count {
+            return Err(invalid(format!(
+                "expected {} ticks, found {}",
+                tick_count, inputs.len(),
+            )));
+        }
+
+        let checksum_interval = read_u32(&mut r)?;
+        if checksum_interval == 0 {
+            return Err(invalid("checksum interval is zero".to_owned()));
+        }
+        let checksum_count = read_u32(&mut r)? as usize;
+        if checksum_count != tick_count / checksum_interval as usize {
+            return Err(invalid(format!(
+                "expected {} checksums, found {}",
+                tick_count / checksum_interval as usize, checksum_count,
+            )));
+        }
+        let mut checksums = Vec::with_capacity(checksum_count);
+        for _ in 0..checksum_count {
+            checksums.push(read_u64(&mut r)?);
+        }
+
+        let replay = Self {
+            game_version,
+            seed,
+            arena,
+            archetypes,
+            spawner,
+            snapshot,
+            inputs,
+            checksum_interval,
+            checksums,
+        };
+        replay.start()?;
+        Ok(replay)
+    }
+}
+
+fn invalid(reason: String) -> GameError {
+    GameError::ResourceLoadError(format!("Invalid replay: {}", reason))
+}
+
+fn read_u8<R: Read>(r: &mut R) -> GameResult<u8> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u16<R: Read>(r: &mut R) -> GameResult<u16> {
+    let mut buf = [0u8; 2];
+    r.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32<R: Read>(r: &mut R) -> GameResult<u32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> GameResult<u64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+
+    /// A replay of `ticks` ticks: standing still, then holding right for
+    /// longer than fits in a single run, then standing still again.
+    fn recorded(ticks: usize) -> Replay {
+        let (arena, archetypes) = (Arena::default(), Arc::new(Archetypes::default()));
+        let spawner = Spawner::endless(Difficulty::default());
+        let simulation = Simulation::new(3, arena, archetypes.clone(), spawner.clone());
+        let mut replay = Replay::new(3, arena, archetypes, spawner);
+        let right = Input { right: true, ..Input::default() };
+        for tick in 0..ticks {
+            let still = tick < 100 || tick + 10 >= ticks;
+            replay.record(if still { Input::default() } else { right }, &simulation);
+        }
+        replay
+    }
+
+    fn written(replay: &Replay) -> Vec<u8> {
+        let mut bytes = vec![];
+        replay.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn reads_back_what_it_wrote() {
+        let replay = recorded(70_000);
+        let bytes = written(&replay);
+        assert_eq!(bytes[..4], Replay::MAGIC);
+        assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), Replay::FORMAT_VERSION);
+        // Runs of the same input take a few bytes, not one per tick.
+        assert!(bytes.len() < replay.inputs().len());
+
+        let read = Replay::read(&bytes[..]).unwrap();
+        assert_eq!(read.seed(), replay.seed());
+        assert!(read.inputs() == replay.inputs());
+        assert_eq!(read.checksums, replay.checksums);
+    }
+
+    #[test]
+    fn rejects_other_format_versions() {
+        let mut bytes = written(&recorded(200));
+        bytes[4] = bytes[4].wrapping_add(1);
+        assert!(Replay::read(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn rejects_a_truncated_file() {
+        let bytes = written(&recorded(200));
+        for &len in [0, 4, bytes.len() / 2, bytes.len() - 1].iter() {
+            assert!(Replay::read(&bytes[..len]).is_err(), "read {} bytes", len);
+        }
+    }
+}
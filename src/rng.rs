@@ -0,0 +1,71 @@
+use rand::{RngCore, SeedableRng};
+
+/// SplitMix64 generator.
+///
+/// Used instead of `StdRng` because its output is fixed by this file alone,
+/// so a seed keeps producing the same game across `rand` upgrades and
+/// platforms.
+#[derive(Clone)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    #[inline]
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    /// Resumes a generator from a value previously returned by `state`.
+    pub fn from_state(state: u64) -> Self {
+        Self { state }
+    }
+
+    /// Derives an independent generator for `stream`, without advancing `self`.
+    /// Lets parallel code draw random numbers deterministically.
+    pub fn fork(&self, stream: u64) -> Self {
+        let mut rng = Self {
+            state: self.state ^ stream.wrapping_mul(0xD1B5_4A32_D192_ED03),
+        };
+        rng.next_u64();
+        rng
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for GameRng {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self::seed_from_u64(u64::from_le_bytes(seed))
+    }
+
+    fn seed_from_u64(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
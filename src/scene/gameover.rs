@@ -1,28 +1,64 @@
 use super::*;
+use crate::actors::ActorId;
+use crate::replay::Replay;
+
+/// How a game came to an end.
+pub enum Ending {
+    /// Id and type of the enemy that hit the player, or `None` if the player
+    /// left an arena that despawns it.
+    Died { killer: Option<(ActorId, &'static str)> },
+    /// The named level was won.
+    Cleared { level: String },
+}
 
 pub struct GameOverScene {
     title: Text,
     body: Text,
     font: Font,
+    replay: Replay,
+    settings: Settings,
 }
 
 impl GameOverScene {
-    pub fn new_box(font: Font) -> SceneBox {
-        let mut title = Text::new("Game Over...\nYou surived for");
+    pub fn new_box(
+        font: Font,
+        replay: Replay,
+        ending: Ending,
+        settings: Settings,
+    ) -> SceneBox {
+        let mut title = Text::new(match ending {
+            Ending::Died { .. } => "Game Over...\nYou surived for",
+            Ending::Cleared { .. } => "Level Cleared!\nYou survived for",
+        });
         title.set_font(font, Scale::uniform(50.0))
             .set_bounds(
-                [800.0, 600.0],
+                [settings.arena.width, settings.arena.height],
                 graphics::Align::Center,
             );
-        let mut body = Text::new("seconds.\nPress [R] to Restart.\nPress [Q] to Quit.");
+        let cause = match ending {
+            Ending::Died { killer: Some((id, type_name)) } => format!(
+                "Killed by {} #{}",
+                type_name.replace('_', " "),
+                id.0,
+            ),
+            Ending::Died { killer: None } => "Left the arena".to_owned(),
+            Ending::Cleared { level } => format!("Cleared {}", level),
+        };
+        let mut body = Text::new(format!(
+            "seconds.\n{}\nSeed: {}\nPress [R] to Restart.\nPress [P] to watch the Replay.\nPress [Q] to Quit.",
+            cause,
+            replay.seed(),
+        ));
         body.set_font(font, Scale::uniform(30.0))
             .set_bounds(
-                [800.0, 600.0],
+                [settings.arena.width, settings.arena.height],
                 graphics::Align::Center,
             );
         let s = Self {
             title, body,
             font,
+            replay,
+            settings,
         };
         Box::new(s)
     }
@@ -44,7 +80,21 @@ impl Scene for GameOverScene {
                 );
                 scene_event_queue.push_back(
                     SceneEvent::Replace(
-                        GameplayScene::new_box(self.font)
+                        GameplayScene::new_box(self.font, self.settings.clone())
+                    )
+                )
+            }
+            KeyCode::P => {
+                scene_event_queue.push_back(
+                    SceneEvent::Pop
+                );
+                scene_event_queue.push_back(
+                    SceneEvent::Replace(
+                        ReplayScene::new_box(
+                            self.font,
+                            self.replay.clone(),
+                            self.settings.clone(),
+                        )
                     )
                 )
             }
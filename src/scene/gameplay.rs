@@ -1,64 +1,184 @@
 use super::*;
-use crate::actors::*;
+use crate::simulation::{Simulation, SimEvent, Input};
+use crate::replay::Replay;
 use ggez::audio::{self, SoundSource};
+use std::io::{Read, Write};
+
+/// Progress of the last few seconds, and how much of it may be rewound.
+struct Rewind {
+    /// `(ticks played, Simulation::save_progress)` from before each tick,
+    /// newest at the back.
+    history: VecDeque<(usize, Vec<u8>)>,
+    /// Seconds of rewinding left.
+    meter: f32,
+}
+
+impl Rewind {
+    const HISTORY_TICKS: usize = 5 * 60;
+    const METER_MAX: f32 = 3.0;
+    const RECHARGE_RATE: f32 = 0.2;
+
+    fn new() -> Self {
+        Self {
+            history: VecDeque::with_capacity(Self::HISTORY_TICKS),
+            meter: Self::METER_MAX,
+        }
+    }
+
+    fn record(&mut self, ticks: usize, progress: Vec<u8>) {
+        if self.history.len() == Self::HISTORY_TICKS {
+            self.history.pop_front();
+        }
+        self.history.push_back((ticks, progress));
+    }
+
+    /// Takes back one tick, if there is history and meter left for it.
+    fn step_back(&mut self, dt: f32) -> Option<(usize, Vec<u8>)> {
+        if self.meter <= 0.0 {
+            return None;
+        }
+        let step = self.history.pop_back()?;
+        self.meter -= dt;
+        Some(step)
+    }
+
+    fn recharge(&mut self, dt: f32) {
+        self.meter = (self.meter + dt * Self::RECHARGE_RATE).min(Self::METER_MAX);
+    }
+
+    fn draw(&self, ctx: &mut Context, arena: &Arena) -> ggez::GameResult {
+        let bar = graphics::Rect::new(
+            10.0, arena.height - 20.0,
+            200.0 * self.meter.max(0.0) / Self::METER_MAX, 10.0,
+        );
+        let mesh = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            bar,
+            [0.4, 0.8, 1.0, 0.75].into(),
+        )?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::default())
+    }
+}
 
 pub struct GameplayScene {
-    player: Player,
-    enemies: Vec<Box<dyn Actor>>,
-    timer: f32,
-    total_time: f32,
+    simulation: Simulation,
+    replay: Replay,
+    rewind: Option<Rewind>,
+    /// Labels every enemy with its id and tags, toggled with [F3].
+    show_ids: bool,
     is_playing: bool,
     is_game_over: bool,
 
     font: Font,
+    settings: Settings,
 }
 
 impl GameplayScene {
-    pub fn new_box(font: Font) -> SceneBox {
+    const QUICKSAVE_PATH: &'static str = "/quicksave.snapshot";
+
+    pub fn new_box(font: Font, settings: Settings) -> SceneBox {
+        let seed = settings.next_seed();
+        let spawner = settings.spawner();
         let s = Self {
-            player: Player::new([400.0,300.0].into(), [18.0, 18.0].into()),
-            enemies: vec![],
-            timer: 5.0,
-            total_time: 0.0,
+            simulation: Simulation::new(
+                seed,
+                settings.arena,
+                settings.archetypes.clone(),
+                spawner.clone(),
+            ),
+            replay: Replay::new(
+                seed,
+                settings.arena,
+                settings.archetypes.clone(),
+                spawner,
+            ),
+            rewind: if settings.rewind { Some(Rewind::new()) } else { None },
+            show_ids: false,
             is_playing: false,
             is_game_over: false,
 
             font,
+            settings,
         };
         Box::new(s)
     }
 
-    fn spawn_bullet(&mut self) {
-        use rand::Rng;
-        let rng = &mut rand::thread_rng();
-        let spawn_pos = match rng.gen_range(0,4) {
-            0 => Point2::new(rng.gen_range(0.0, 800.0), 1.0),
-            1 => Point2::new(rng.gen_range(0.0, 800.0), 599.0),
-            2 => Point2::new(1.0, rng.gen_range(0.0, 600.0)),
-            _ => Point2::new(799.0, rng.gen_range(0.0, 600.0)),
-        };
-        let dir_vec = self.player.get_pos() - spawn_pos;
-        let vel_vec = dir_vec.normalize() * rng.gen_range(50.0, 150.0);
-        let size = rng.gen_range(5.0, 15.0);
-        let bullet: Box<dyn Actor> = match rng.gen_range(0, 100) {
-            0..=74 => Box::new(Bullet::new(
-                spawn_pos,
-                [size, size].into(),
-                vel_vec,
-            )),
-            75..=89 => Box::new(DrunkBullet::new(
-                spawn_pos,
-                [size, size].into(),
-                vel_vec,
-            )),
-            _ => Box::new(HomingBullet::new(
-                spawn_pos,
-                [size, size].into(),
-                vel_vec,
-                self.player.get_pos(),
-            )),
+    /// Continues a game saved with `Simulation::save_snapshot`.
+    pub fn resume_box(
+        font: Font,
+        mut settings: Settings,
+        snapshot: Vec<u8>,
+    ) -> ggez::GameResult<SceneBox> {
+        let simulation = Simulation::load_snapshot(&snapshot)?;
+        // Restarts should stay in the mode of the saved game.
+        settings.arena = *simulation.arena();
+        if let Some(difficulty) = simulation.spawner().difficulty() {
+            settings.difficulty = difficulty;
+        }
+        let s = Self {
+            replay: Replay::from_snapshot(
+                simulation.seed(),
+                *simulation.arena(),
+                simulation.archetypes().clone(),
+                simulation.spawner().clone(),
+                snapshot,
+            ),
+            simulation,
+            rewind: if settings.rewind { Some(Rewind::new()) } else { None },
+            show_ids: false,
+            is_playing: false,
+            is_game_over: false,
+
+            font,
+            settings,
         };
-        self.enemies.push(bullet);
+        Ok(Box::new(s))
+    }
+
+    fn save_replay(&self, ctx: &mut Context) -> ggez::GameResult {
+        let file = ggez::filesystem::create(ctx, "/last.replay")?;
+        self.replay.write(std::io::BufWriter::new(file))
+    }
+
+    fn quicksave(&self, ctx: &mut Context) -> ggez::GameResult {
+        let mut file = ggez::filesystem::create(ctx, Self::QUICKSAVE_PATH)?;
+        file.write_all(&self.simulation.save_snapshot())?;
+        Ok(())
+    }
+
+    fn quickload(&self, ctx: &mut Context) -> ggez::GameResult<SceneBox> {
+        let mut snapshot = vec![];
+        ggez::filesystem::open(ctx, Self::QUICKSAVE_PATH)?
+            .read_to_end(&mut snapshot)?;
+        Self::resume_box(self.font, self.settings.clone(), snapshot)
+    }
+
+    fn draw_ids(&self, ctx: &mut Context) -> ggez::GameResult {
+        let enemies = self.simulation.enemies();
+        for (id, enemy) in enemies.iter() {
+            let mut label = Text::new(format!("#{} {}", id.0, enemies.tags(id).join(",")));
+            label.set_font(self.font, Scale::uniform(16.0));
+            let rect = enemy.get_rect();
+            graphics::draw(
+                ctx,
+                &label,
+                graphics::DrawParam::default()
+                    .dest(Point2::new(rect.right() + 2.0, rect.top()))
+                    .color([1.0, 1.0, 1.0, 0.75].into())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn read_input(ctx: &Context) -> Input {
+    let keyset = keyboard::pressed_keys(ctx);
+    Input {
+        up: keyset.contains(&KeyCode::W),
+        down: keyset.contains(&KeyCode::S),
+        left: keyset.contains(&KeyCode::A),
+        right: keyset.contains(&KeyCode::D),
     }
 }
 
@@ -69,133 +189,116 @@ impl Scene for GameplayScene {
         dt: f32,
         scene_event_queue: &mut VecDeque<SceneEvent>
     ) -> ggez::GameResult {
-        self.total_time += dt;
-
-        self.timer -= dt;
-        if self.timer <= 0.0 {
-            self.timer += 5.0;
-            self.spawn_bullet();
-        }
-
-        const VELOCITY_SCALAR: f32 = 150.0;
-        let keyset = keyboard::pressed_keys(ctx);
-        let mut dir = Point2::new(0.0, 0.0);
-        if keyset.contains(&KeyCode::W) {
-            dir += Vector2::new(0.0, -1.0);
-        }
-        if keyset.contains(&KeyCode::S) {
-            dir += Vector2::new(0.0, 1.0);
-        }
-        if keyset.contains(&KeyCode::A) {
-            dir += Vector2::new(-1.0, 0.0);
-        }
-        if keyset.contains(&KeyCode::D) {
-            dir += Vector2::new(1.0, 0.0);
-        }
-
-        if dir.x == 0.0 && dir.y == 0.0 {
-            self.player.set_vel(Vector2::new(0.0, 0.0));
-        }
-        else {
-            self.player.set_vel(
-                (dir - Point2::new(0.0, 0.0)).normalize() * VELOCITY_SCALAR
+        if let Some(rewind) = &mut self.rewind {
+            if keyboard::pressed_keys(ctx).contains(&KeyCode::Space) {
+                if let Some((ticks, progress)) = rewind.step_back(dt) {
+                    self.simulation.restore_progress(&progress)?;
+                    self.replay.truncate(ticks);
+                    return Ok(());
+                }
+            }
+            else {
+                rewind.recharge(dt);
+            }
+            rewind.record(
+                self.replay.inputs().len(),
+                self.simulation.save_progress(),
             );
         }
-        self.player.update(dt);
-        let mut pos = self.player.get_pos();
-        pos.x = na::wrap(pos.x, 0.0, 800.0);
-        pos.y = na::wrap(pos.y, 0.0, 600.0);
-        self.player.set_pos(pos);
-
-        let player_rect = self.player.get_rect();
-        self.is_game_over = self.enemies
-            .par_iter_mut()
-            .update(|enemy| {
-                enemy.update(dt);
-                let mut pos = enemy.get_pos();
-                pos.x = na::wrap(pos.x, 0.0, 800.0);
-                pos.y = na::wrap(pos.y, 0.0, 600.0);
-                enemy.set_pos(pos);
-            })
-            .any(|enemy| {
-                let enemy_rect = enemy.get_rect();
-                player_rect.overlaps(&enemy_rect)
-            });
-        
-        if self.is_game_over {
+
+        let input = read_input(ctx);
+        let events = self.simulation.tick(dt, input);
+        self.replay.record(input, &self.simulation);
+        for event in events {
+            let ending = match event {
+                SimEvent::PlayerDied { killer } => Ending::Died { killer },
+                SimEvent::LevelCleared => Ending::Cleared {
+                    level: self.simulation.spawner()
+                        .level_name()
+                        .unwrap_or_default()
+                        .to_owned(),
+                },
+            };
+            let died = matches!(ending, Ending::Died { .. });
+            self.is_game_over = true;
+            if let Err(e) = self.save_replay(ctx) {
+                eprintln!("Failed to save replay: {}", e);
+            }
             scene_event_queue.push_back(
                 SceneEvent::Push(
-                    GameOverScene::new_box(self.font)
+                    GameOverScene::new_box(
+                        self.font,
+                        self.replay.clone(),
+                        ending,
+                        self.settings.clone(),
+                    )
                 )
             );
 
-            let explosion_sound = audio::SoundData::from_bytes(
-                include_bytes!("../../resources/explosion.wav")
-            );
-            audio::Source::from_data(ctx, explosion_sound)?
-                .play_detached()?;
-        }
-        else {
-            let mut new_enemies: Vec<_> = self.enemies
-                .par_iter()
-                .filter_map(|enemy| {
-                    if enemy.has_action() {
-                        enemy.action(
-                            dt,
-                            &self.player,
-                            &self.enemies,
-                        )
-                    }
-                    else {
-                        None
-                    }
-                })
-                .flatten()
-                .collect();
-            self.enemies.append(&mut new_enemies);
+            if died {
+                let explosion_sound = audio::SoundData::from_bytes(
+                    include_bytes!("../../resources/explosion.wav")
+                );
+                audio::Source::from_data(ctx, explosion_sound)?
+                    .play_detached()?;
+            }
         }
 
         Ok(())
     }
 
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        key: KeyCode,
+        _: KeyMods,
+        _: bool,
+        scene_event_queue: &mut VecDeque<SceneEvent>
+    ) {
+        if self.is_game_over {
+            return;
+        }
+        match key {
+            KeyCode::F5 => {
+                if let Err(e) = self.quicksave(ctx) {
+                    eprintln!("Failed to save snapshot: {}", e);
+                }
+            }
+            KeyCode::F3 => self.show_ids = !self.show_ids,
+            KeyCode::F9 => {
+                match self.quickload(ctx) {
+                    Ok(scene_box) => scene_event_queue.push_back(
+                        SceneEvent::Replace(scene_box)
+                    ),
+                    Err(e) => eprintln!("Failed to load snapshot: {}", e),
+                }
+            }
+            _ => ()
+        }
+    }
+
     fn draw(
         &mut self,
         ctx: &mut Context
     ) -> ggez::GameResult {
         if self.is_playing {
-            let mut timer_text = Text::new(format!("{:.0}", self.total_time));
-            timer_text.set_font(self.font, Scale::uniform(200.0))
-                .set_bounds(
-                    [800.0, 600.0],
-                    graphics::Align::Center
-                );
-            let h = timer_text.height(ctx);
-            graphics::draw(
+            draw_timer(
                 ctx,
-                &timer_text,
-                graphics::DrawParam::default()
-                    .dest([0.0, 320.0 - h as f32 * 0.5])
-                    .color( if !self.is_game_over {
-                        [1.0, 1.0, 1.0, 0.25].into()
-                    } else {
-                        [1.0, 1.0, 1.0, 0.75].into()
-                    })
+                self.font,
+                self.simulation.arena(),
+                self.simulation.total_time(),
+                self.is_game_over,
             )?;
-            graphics::pop_transform(ctx);
-            graphics::apply_transformations(ctx)?;
         }
 
-        let mesh_builder = &mut graphics::MeshBuilder::new();
-        self.player.draw(ctx, Some(mesh_builder))?;
-        for enemy in self.enemies.iter() {
-            enemy.draw(ctx, Some(mesh_builder))?;
+        self.simulation.draw(ctx)?;
+
+        if let Some(rewind) = &self.rewind {
+            rewind.draw(ctx, self.simulation.arena())?;
+        }
+        if self.show_ids {
+            self.draw_ids(ctx)?;
         }
-        let mesh = mesh_builder.build(ctx)?;
-        graphics::draw(
-            ctx,
-            &mesh,
-            graphics::DrawParam::default()
-        )?;
         Ok(())
     }
 
@@ -1,4 +1,5 @@
 use std::collections::VecDeque;
+use std::sync::Arc;
 
 use ggez::Context;
 use ggez::graphics::{self, Font, Text, Scale};
@@ -6,11 +7,56 @@ use ggez::input::keyboard::{
     self, KeyCode, KeyMods,
 };
 use ggez::nalgebra as na;
-use na::{Point2, Vector2};
-use rayon::prelude::*;
+use na::Point2;
+
+use crate::archetype::Archetypes;
+use crate::arena::Arena;
+use crate::difficulty::Difficulty;
+use crate::level::Level;
+use crate::spawner::Spawner;
 
 pub type SceneBox = Box<dyn Scene>;
 
+/// Options chosen at startup that every new run should respect.
+#[derive(Clone, Default)]
+pub struct Settings {
+    /// Fixed RNG seed. A fresh random seed is used for each run if `None`.
+    pub seed: Option<u64>,
+    /// Casual mode, where the last few seconds can be rewound.
+    pub rewind: bool,
+    pub arena: Arena,
+    /// What the spawner picks from, as loaded from `archetypes.ron`.
+    pub archetypes: Arc<Archetypes>,
+    /// Every level that can be picked, as loaded from `levels/`.
+    pub levels: Arc<Vec<Arc<Level>>>,
+    /// Index into `levels` of the level to play, or `None` for endless mode.
+    pub level: Option<usize>,
+    /// How endless mode escalates.
+    pub difficulty: Difficulty,
+}
+
+impl Settings {
+    pub fn next_seed(&self) -> u64 {
+        self.seed.unwrap_or_else(rand::random)
+    }
+
+    pub fn spawner(&self) -> Spawner {
+        match self.level {
+            Some(i) => Spawner::level(self.levels[i].clone()),
+            None => Spawner::endless(self.difficulty),
+        }
+    }
+
+    /// Picks the next level, going back to endless mode after the last one.
+    pub fn next_level(&mut self) {
+        self.level = match self.level {
+            None if !self.levels.is_empty() => Some(0),
+            Some(i) if i + 1 < self.levels.len() => Some(i + 1),
+            _ => None,
+        };
+    }
+}
+
 pub trait Scene {
     fn update(
         &mut self,
@@ -47,6 +93,37 @@ pub trait Scene {
     fn draw_in_background(&self) -> bool { false }
 }
 
+/// Draws the big survival timer behind the gameplay.
+fn draw_timer(
+    ctx: &mut Context,
+    font: Font,
+    arena: &Arena,
+    seconds: f32,
+    highlight: bool,
+) -> ggez::GameResult {
+    let mut timer_text = Text::new(format!("{:.0}", seconds));
+    timer_text.set_font(font, Scale::uniform(200.0))
+        .set_bounds(
+            [arena.width, arena.height],
+            graphics::Align::Center
+        );
+    let h = timer_text.height(ctx);
+    graphics::draw(
+        ctx,
+        &timer_text,
+        graphics::DrawParam::default()
+            .dest([0.0, arena.height / 2.0 + 20.0 - h as f32 * 0.5])
+            .color( if !highlight {
+                [1.0, 1.0, 1.0, 0.25].into()
+            } else {
+                [1.0, 1.0, 1.0, 0.75].into()
+            })
+    )?;
+    graphics::pop_transform(ctx);
+    graphics::apply_transformations(ctx)?;
+    Ok(())
+}
+
 #[allow(dead_code)]
 pub enum SceneEvent {
     Pop,
@@ -57,6 +134,8 @@ pub enum SceneEvent {
 mod starting;
 mod gameplay;
 mod gameover;
+mod replay;
 pub use starting::StartingScene;
 pub use gameplay::GameplayScene;
-pub use gameover::GameOverScene;
\ No newline at end of file
+pub use gameover::{GameOverScene, Ending};
+pub use replay::ReplayScene;
\ No newline at end of file
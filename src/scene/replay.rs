@@ -0,0 +1,158 @@
+use super::*;
+use crate::simulation::{Simulation, SimEvent};
+use crate::replay::Replay;
+
+pub struct ReplayScene {
+    simulation: Simulation,
+    replay: Replay,
+    tick: usize,
+    is_finished: bool,
+    /// Last tick checked to match the recording and the tick it was found not to.
+    desync_ticks: Option<(usize, usize)>,
+    title: Text,
+    body: Text,
+
+    font: Font,
+    settings: Settings,
+}
+
+impl ReplayScene {
+    pub fn new_box(font: Font, replay: Replay, settings: Settings) -> SceneBox {
+        let simulation = replay.start()
+            .expect("replay start state is checked when the replay is read");
+        let arena = *simulation.arena();
+        let mut title = Text::new(format!("Replay - Seed: {}", replay.seed()));
+        title.set_font(font, Scale::uniform(30.0));
+        let mut body = Text::new("Replay finished.\nPress [Space] to watch again.\nPress [R] to Play.\nPress [Q] to Quit.");
+        body.set_font(font, Scale::uniform(30.0))
+            .set_bounds(
+                [arena.width, arena.height],
+                graphics::Align::Center,
+            );
+        let s = Self {
+            simulation,
+            replay,
+            tick: 0,
+            is_finished: false,
+            desync_ticks: None,
+            title, body,
+
+            font,
+            settings,
+        };
+        Box::new(s)
+    }
+}
+
+impl Scene for ReplayScene {
+    fn update(
+        &mut self,
+        _ctx: &mut Context,
+        dt: f32,
+        _scene_event_queue: &mut VecDeque<SceneEvent>
+    ) -> ggez::GameResult {
+        if self.is_finished {
+            return Ok(());
+        }
+        match self.replay.inputs().get(self.tick) {
+            Some(&input) => {
+                self.tick += 1;
+                for event in self.simulation.tick(dt, input) {
+                    match event {
+                        SimEvent::PlayerDied { .. }
+                        | SimEvent::LevelCleared => self.is_finished = true,
+                    }
+                }
+                if self.desync_ticks.is_none()
+                    && !self.replay.verify(self.tick, &self.simulation)
+                {
+                    let ticks = (self.tick - self.replay.checksum_interval(), self.tick);
+                    eprintln!("Replay desynced between ticks {} and {}", ticks.0, ticks.1);
+                    self.desync_ticks = Some(ticks);
+                }
+            }
+            None => self.is_finished = true,
+        }
+        Ok(())
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        key: KeyCode,
+        _: KeyMods,
+        _: bool,
+        scene_event_queue: &mut VecDeque<SceneEvent>
+    ) {
+        match key {
+            KeyCode::Space => {
+                scene_event_queue.push_back(
+                    SceneEvent::Replace(
+                        ReplayScene::new_box(
+                            self.font,
+                            self.replay.clone(),
+                            self.settings.clone(),
+                        )
+                    )
+                )
+            }
+            KeyCode::R => {
+                scene_event_queue.push_back(
+                    SceneEvent::Replace(
+                        GameplayScene::new_box(self.font, self.settings.clone())
+                    )
+                )
+            }
+            KeyCode::Q | KeyCode::Escape => {
+                ggez::event::quit(ctx)
+            }
+            _ => ()
+        }
+    }
+
+    fn draw(
+        &mut self,
+        ctx: &mut Context
+    ) -> ggez::GameResult {
+        draw_timer(
+            ctx,
+            self.font,
+            self.simulation.arena(),
+            self.simulation.total_time(),
+            self.is_finished,
+        )?;
+        self.simulation.draw(ctx)?;
+
+        graphics::draw(
+            ctx,
+            &self.title,
+            graphics::DrawParam::default()
+                .dest([10.0, 10.0])
+                .color([1.0, 1.0, 1.0, 0.75].into())
+        )?;
+        if let Some((matched, diverged)) = self.desync_ticks {
+            let mut desync_text = Text::new(format!(
+                "Desynced between ticks {} and {}",
+                matched, diverged,
+            ));
+            desync_text.set_font(self.font, Scale::uniform(30.0));
+            graphics::draw(
+                ctx,
+                &desync_text,
+                graphics::DrawParam::default()
+                    .dest([10.0, 40.0])
+                    .color([1.0, 0.2, 0.2, 1.0].into())
+            )?;
+        }
+        if self.is_finished {
+            let body_pos = Point2::new(0.0, 450.0);
+            graphics::draw(
+                ctx,
+                &self.body,
+                graphics::DrawParam::default()
+                    .dest(body_pos)
+            )?;
+        }
+        Ok(())
+    }
+}
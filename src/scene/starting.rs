@@ -3,27 +3,67 @@ use super::*;
 pub struct StartingScene {
     title: Text,
     body: Text,
+    mode: Text,
+
+    font: Font,
+    settings: Settings,
+    /// Set when the game below was resumed, so its mode can't change.
+    is_resumed: bool,
+    settings_changed: bool,
 }
 
 impl StartingScene {
-    pub fn new_box(font: Font) -> SceneBox {
+    pub fn new_box(font: Font, settings: Settings, is_resumed: bool) -> SceneBox {
         let mut title = Text::new("Dodge Bullet");
         title.set_font(font, Scale::uniform(80.0))
             .set_bounds(
-                [800.0, 600.0],
+                [settings.arena.width, settings.arena.height],
                 graphics::Align::Center,
             );
         let mut body = Text::new("[W,A,S,D] to move.\nTry to dodge the bullets for as long as possible.");
         body.set_font(font, Scale::uniform(30.0))
             .set_bounds(
-                [800.0, 600.0],
+                [settings.arena.width, settings.arena.height],
                 graphics::Align::Center,
             );
-        let s = Self {
+        let mut s = Self {
             title, body,
+            mode: Text::new(""),
+            font,
+            settings,
+            is_resumed,
+            settings_changed: false,
         };
+        s.update_mode_text();
         Box::new(s)
     }
+
+    fn update_mode_text(&mut self) {
+        let text = if self.is_resumed {
+            "Resuming a saved game.".to_owned()
+        }
+        else {
+            format!(
+                "[C] Casual mode, hold [Space] to rewind: {}\n[B] Arena: {}\n[L] Level: {}\n[V] Difficulty: {}",
+                if self.settings.rewind { "On" } else { "Off" },
+                self.settings.arena.name(),
+                match self.settings.level {
+                    Some(i) => self.settings.levels[i].name(),
+                    None => "Endless",
+                },
+                match self.settings.level {
+                    Some(_) => "Set by the level",
+                    None => self.settings.difficulty.name(),
+                },
+            )
+        };
+        self.mode = Text::new(text);
+        self.mode.set_font(self.font, Scale::uniform(30.0))
+            .set_bounds(
+                [self.settings.arena.width, self.settings.arena.height],
+                graphics::Align::Center,
+            );
+    }
 }
 
 impl Scene for StartingScene {
@@ -41,8 +81,35 @@ impl Scene for StartingScene {
             | KeyCode::Space => {
                 scene_event_queue.push_back(
                     SceneEvent::Pop
-                )
+                );
+                if self.settings_changed {
+                    scene_event_queue.push_back(
+                        SceneEvent::Replace(
+                            GameplayScene::new_box(self.font, self.settings.clone())
+                        )
+                    );
+                }
             },
+            KeyCode::C if !self.is_resumed => {
+                self.settings.rewind = !self.settings.rewind;
+                self.settings_changed = true;
+                self.update_mode_text();
+            }
+            KeyCode::B if !self.is_resumed => {
+                self.settings.arena = self.settings.arena.next_mode();
+                self.settings_changed = true;
+                self.update_mode_text();
+            }
+            KeyCode::L if !self.is_resumed => {
+                self.settings.next_level();
+                self.settings_changed = true;
+                self.update_mode_text();
+            }
+            KeyCode::V if !self.is_resumed => {
+                self.settings.difficulty = self.settings.difficulty.next_preset();
+                self.settings_changed = true;
+                self.update_mode_text();
+            }
             KeyCode::Q | KeyCode::Escape => {
                 ggez::event::quit(ctx)
             }
@@ -71,7 +138,13 @@ impl Scene for StartingScene {
             graphics::DrawParam::default()
                 .dest(body_pos)
         )?;
+        let mode_pos = Point2::new(0.0, 450.0);
+        graphics::draw(
+            ctx,
+            &self.mode,
+            graphics::DrawParam::default()
+                .dest(mode_pos)
+        )?;
         Ok(())
     }
 }
-
@@ -0,0 +1,669 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use ggez::graphics;
+use ggez::nalgebra as na;
+use na::{Point2, Vector2};
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::actors::*;
+use crate::archetype::Archetypes;
+use crate::arena::{Arena, Boundary};
+use crate::rng::GameRng;
+use crate::spatial::SpatialGrid;
+use crate::spawner::Spawner;
+use crate::collision::{swept_bounds, swept_intersects};
+use crate::state::{self, StateReader, StateWriter};
+
+/// Directional input for a single tick.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Input {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+}
+
+impl Input {
+    pub fn dir(&self) -> Vector2<f32> {
+        let mut dir = Vector2::new(0.0, 0.0);
+        if self.up {
+            dir += Vector2::new(0.0, -1.0);
+        }
+        if self.down {
+            dir += Vector2::new(0.0, 1.0);
+        }
+        if self.left {
+            dir += Vector2::new(-1.0, 0.0);
+        }
+        if self.right {
+            dir += Vector2::new(1.0, 0.0);
+        }
+        dir
+    }
+
+    pub fn to_bits(self) -> u8 {
+        self.up as u8
+            | (self.down as u8) << 1
+            | (self.left as u8) << 2
+            | (self.right as u8) << 3
+    }
+
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        if bits > 0b1111 {
+            return None;
+        }
+        Some(Self {
+            up: bits & 0b0001 != 0,
+            down: bits & 0b0010 != 0,
+            left: bits & 0b0100 != 0,
+            right: bits & 0b1000 != 0,
+        })
+    }
+}
+
+/// Things that happened during a tick, for the caller to react to.
+pub enum SimEvent {
+    PlayerDied {
+        /// Id and `Actor::type_name` of the enemy that hit the player, or
+        /// `None` if the player left an arena that despawns it.
+        killer: Option<(ActorId, &'static str)>,
+    },
+    /// The goal of the level was reached.
+    LevelCleared,
+}
+
+/// The game rules, independent of any window, keyboard or audio device.
+pub struct Simulation {
+    player: Player,
+    enemies: ActorSet,
+    spawner: Spawner,
+    total_time: f32,
+    seed: u64,
+    rng: GameRng,
+    arena: Arena,
+    /// What the spawner picks from. Saved along with the game, so that
+    /// retuning the archetypes file doesn't change old snapshots and replays.
+    archetypes: Arc<Archetypes>,
+    /// Enemy positions as of the last tick, rebuilt every tick.
+    grid: SpatialGrid,
+}
+
+impl Simulation {
+    const GRID_CELL_SIZE: f32 = 32.0;
+    const SNAPSHOT_MAGIC: [u8; 4] = *b"LDSN";
+    const SNAPSHOT_VERSION: u8 = 10;
+
+    /// The same `seed`, `arena`, `archetypes`, `spawner` and inputs always
+    /// play out the same game.
+    pub fn new(
+        seed: u64,
+        arena: Arena,
+        archetypes: Arc<Archetypes>,
+        spawner: Spawner,
+    ) -> Self {
+        Self {
+            player: Player::new(
+                [arena.width / 2.0, arena.height / 2.0].into(),
+                [18.0, 18.0].into(),
+            ),
+            enemies: ActorSet::new(),
+            spawner,
+            total_time: 0.0,
+            seed,
+            rng: GameRng::seed_from_u64(seed),
+            arena,
+            archetypes,
+            grid: SpatialGrid::new(arena, Self::GRID_CELL_SIZE),
+        }
+    }
+
+    #[inline]
+    pub fn total_time(&self) -> f32 {
+        self.total_time
+    }
+
+    /// The seed the game was originally started with.
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    #[inline]
+    pub fn enemies(&self) -> &ActorSet {
+        &self.enemies
+    }
+
+    #[inline]
+    pub fn arena(&self) -> &Arena {
+        &self.arena
+    }
+
+    #[inline]
+    pub fn archetypes(&self) -> &Arc<Archetypes> {
+        &self.archetypes
+    }
+
+    #[inline]
+    pub fn spawner(&self) -> &Spawner {
+        &self.spawner
+    }
+
+    /// Hash of the complete game state, used to detect desyncs.
+    pub fn checksum(&self) -> u64 {
+        let mut w = StateWriter::new();
+        self.write_state(&mut w);
+        w.checksum()
+    }
+
+    /// Serializes the whole game, so it can be resumed with `load_snapshot`.
+    pub fn save_snapshot(&self) -> Vec<u8> {
+        let mut w = StateWriter::new();
+        for &byte in Self::SNAPSHOT_MAGIC.iter() {
+            w.write_u8(byte);
+        }
+        w.write_u8(Self::SNAPSHOT_VERSION);
+        w.write_str(env!("CARGO_PKG_VERSION"));
+        self.write_state(&mut w);
+        w.into_bytes()
+    }
+
+    pub fn load_snapshot(bytes: &[u8]) -> ggez::GameResult<Self> {
+        let r = &mut StateReader::new(bytes);
+        let mut magic = [0u8; 4];
+        for byte in magic.iter_mut() {
+            *byte = r.read_u8()?;
+        }
+        if magic != Self::SNAPSHOT_MAGIC {
+            return Err(state::invalid("not a snapshot".to_owned()));
+        }
+        let version = r.read_u8()?;
+        if version != Self::SNAPSHOT_VERSION {
+            return Err(state::invalid(format!(
+                "snapshot version {} is not supported (expected {})",
+                version, Self::SNAPSHOT_VERSION,
+            )));
+        }
+        let game_version = r.read_str()?;
+        if game_version != env!("CARGO_PKG_VERSION") {
+            return Err(state::invalid(format!(
+                "snapshot was saved by version {}, this is version {}",
+                game_version, env!("CARGO_PKG_VERSION"),
+            )));
+        }
+        let s = Self::read_state(r)?;
+        if !r.is_empty() {
+            return Err(state::invalid("trailing data after snapshot".to_owned()));
+        }
+        Ok(s)
+    }
+
+    /// Serializes what changes from tick to tick, leaving out the arena,
+    /// archetypes and level, for `restore_progress` to rewind this same game.
+    pub fn save_progress(&self) -> Vec<u8> {
+        let mut w = StateWriter::new();
+        self.player.write_state(&mut w);
+        self.spawner.write_progress(&mut w);
+        w.write_f32(self.total_time);
+        w.write_u64(self.rng.state());
+        self.enemies.write_state(&mut w);
+        w.into_bytes()
+    }
+
+    pub fn restore_progress(&mut self, bytes: &[u8]) -> ggez::GameResult {
+        let r = &mut StateReader::new(bytes);
+        let player = Player::read_state(r)?;
+        let spawner = self.spawner.read_progress(r)?;
+        let total_time = r.read_f32()?;
+        let rng = GameRng::from_state(r.read_u64()?);
+        let enemies = ActorSet::read_state(r)?;
+        if !r.is_empty() {
+            return Err(state::invalid("trailing data after progress".to_owned()));
+        }
+        self.player = player;
+        self.spawner = spawner;
+        self.total_time = total_time;
+        self.rng = rng;
+        self.enemies = enemies;
+        Ok(())
+    }
+
+    fn write_state(&self, w: &mut StateWriter) {
+        self.player.write_state(w);
+        self.spawner.write_state(w);
+        w.write_f32(self.total_time);
+        w.write_u64(self.seed);
+        w.write_u64(self.rng.state());
+        self.arena.write_state(w);
+        self.archetypes.write_state(w);
+        self.enemies.write_state(w);
+    }
+
+    fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        let player = Player::read_state(r)?;
+        let spawner = Spawner::read_state(r)?;
+        let total_time = r.read_f32()?;
+        let seed = r.read_u64()?;
+        let rng = GameRng::from_state(r.read_u64()?);
+        let arena = Arena::read_state(r)?;
+        let archetypes = Arc::new(Archetypes::read_state(r)?);
+        spawner.check(&archetypes).map_err(state::invalid)?;
+        let enemies = ActorSet::read_state(r)?;
+        Ok(Self {
+            player,
+            enemies,
+            spawner,
+            total_time,
+            seed,
+            rng,
+            arena,
+            archetypes,
+            grid: SpatialGrid::new(arena, Self::GRID_CELL_SIZE),
+        })
+    }
+
+    /// Advances the game by one tick of `dt` seconds.
+    pub fn tick(&mut self, dt: f32, input: Input) -> Vec<SimEvent> {
+        let mut events = vec![];
+        self.total_time += dt;
+
+        let spawned = self.spawner.tick(
+            dt,
+            self.total_time,
+            &mut self.rng,
+            &self.archetypes,
+            &self.arena,
+            self.player.get_pos(),
+        );
+        for enemy in spawned {
+            self.enemies.insert(enemy);
+        }
+
+        const VELOCITY_SCALAR: f32 = 150.0;
+        let dir = input.dir();
+        if dir.x == 0.0 && dir.y == 0.0 {
+            self.player.set_vel(self.player.drift());
+        }
+        else {
+            self.player.set_vel(dir.normalize() * VELOCITY_SCALAR + self.player.drift());
+        }
+        let arena = self.arena;
+        let start = self.player.get_pos();
+        self.player.update(dt);
+        let player_motion = arena.confine(arena.player, &mut self.player, start);
+
+        // Update every enemy before testing collisions, so that an early
+        // hit can't leave the rest of the enemies a tick behind.
+        let moved: Vec<_> = self.enemies
+            .par_iter_mut()
+            .map(|(enemy, life)| {
+                let start = enemy.get_pos();
+                enemy.update(dt);
+                let exited = !arena.rect().contains(enemy.get_pos());
+                let motion = if life.expiry().on_exit && exited {
+                    // Let it drift off while it fades, rather than have the
+                    // arena bring it back in.
+                    enemy.get_pos() - start
+                }
+                else {
+                    let boundary = arena.boundary_of(enemy.as_ref());
+                    arena.confine(boundary, enemy.as_mut(), start)?
+                };
+                let wrapped = enemy.get_pos() - start != motion;
+                if life.advance(dt, motion, wrapped, exited) {
+                    Some(motion)
+                }
+                else {
+                    None
+                }
+            })
+            .collect();
+        let kept: Vec<_> = moved.iter().map(Option::is_some).collect();
+        self.enemies.retain(&kept);
+        let motions: Vec<_> = moved.into_iter().flatten().collect();
+
+        self.grid.rebuild(self.enemies.actors(), &motions);
+        // `Some` if the player died, with the index of the enemy that did it.
+        let hit = match player_motion {
+            // Leaving an arena that despawns the player is as good as a hit.
+            None => Some(None),
+            Some(player_motion) => {
+                let player_shape = self.player.shape();
+                let player_bounds =
+                    swept_bounds(player_shape.bounds(), player_motion);
+                self.grid
+                    .candidates(player_bounds)
+                    .into_iter()
+                    .filter(|&i| !self.enemies.lives()[i].is_fading())
+                    .filter(|&i| self.enemies.actors()[i].is_lethal())
+                    .find(|&i| {
+                        let enemy = self.enemies.actors()[i].as_ref();
+                        let boundary = arena.boundary_with_player(enemy);
+                        let shape = enemy.shape();
+                        let bounds = swept_bounds(shape.bounds(), motions[i]);
+                        arena.offsets(boundary, player_bounds, bounds)
+                            .into_iter()
+                            .any(|offset| swept_intersects(
+                                &player_shape,
+                                player_motion,
+                                &shape.translate(offset),
+                                motions[i],
+                            ))
+                    })
+                    .map(Some)
+            }
+        };
+
+        if let Some(killer) = hit {
+            events.push(SimEvent::PlayerDied {
+                killer: killer.map(|i| (
+                    self.enemies.ids()[i],
+                    self.enemies.actors()[i].type_name(),
+                )),
+            });
+        }
+        else {
+            // Each enemy gets its own stream so the parallel pass stays
+            // reproducible no matter how rayon schedules it.
+            let tick_rng = GameRng::seed_from_u64(self.rng.gen());
+            let world = World {
+                player: &self.player,
+                enemies: &self.enemies,
+                grid: &self.grid,
+                arena: &arena,
+            };
+            let enemies = &self.enemies;
+            let actions: Vec<_> = enemies.actors()
+                .par_iter()
+                .enumerate()
+                .map(|(i, enemy)| {
+                    if !enemy.has_action() || enemies.lives()[i].is_fading() {
+                        return Action::default();
+                    }
+                    let id = enemies.ids()[i];
+                    enemy.sense(id, &world, &mut tick_rng.fork(id.0))
+                })
+                .collect();
+
+            let mut intents = Vec::with_capacity(actions.len());
+            let mut commands = Vec::with_capacity(actions.len());
+            for (&id, action) in self.enemies.ids().iter().zip(actions) {
+                intents.push(action.intent);
+                commands.push((id, action.commands));
+            }
+            // Each enemy only applies its own intent, so this can't depend
+            // on the order rayon runs them in either.
+            self.enemies
+                .par_iter_mut()
+                .zip(intents)
+                .for_each(|((enemy, _), intent)| {
+                    if let Some(intent) = intent {
+                        enemy.apply(intent);
+                    }
+                });
+            self.apply_commands(commands, dt);
+
+            if self.spawner.is_cleared(self.total_time, &self.enemies) {
+                events.push(SimEvent::LevelCleared);
+            }
+        }
+
+        events
+    }
+
+    /// Carries out the commands the actors sensed, in order, except that
+    /// pushes are summed per actor and applied together at the end.
+    fn apply_commands(
+        &mut self,
+        commands: Vec<(ActorId, Vec<Command>)>,
+        dt: f32,
+    ) {
+        let mut spawned = vec![];
+        let mut forces: BTreeMap<ActorId, Vector2<f32>> = BTreeMap::new();
+        for (source, commands) in commands {
+            for command in commands {
+                match command {
+                    Command::Spawn(enemy) => spawned.push(enemy),
+                    Command::DespawnSelf => self.enemies.expire(source),
+                    Command::Despawn(id) => self.enemies.expire(id),
+                    Command::Modify(id, modify) => {
+                        if id == ActorId::PLAYER {
+                            modify(&mut self.player);
+                        }
+                        else if let Some(enemy) = self.enemies.get_mut(id) {
+                            modify(enemy);
+                        }
+                    }
+                    Command::Push(id, force) => {
+                        *forces.entry(id).or_insert_with(Vector2::zeros) += force;
+                    }
+                }
+            }
+        }
+        for (id, force) in forces {
+            if id == ActorId::PLAYER {
+                self.player.push(force * dt);
+            }
+            else if let Some(enemy) = self.enemies.get_mut(id) {
+                enemy.set_vel(enemy.get_vel() + force * dt);
+            }
+        }
+        for enemy in spawned {
+            self.enemies.insert(enemy);
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        // Whatever wraps goes in one mesh and the rest in another, each only
+        // built if something was drawn into it.
+        let mut wrapping = None;
+        let mut confined = None;
+        let mesh_builder = if self.arena.player == Boundary::Wrap {
+            &mut wrapping
+        }
+        else {
+            &mut confined
+        };
+        let mesh_builder = mesh_builder.get_or_insert_with(graphics::MeshBuilder::new);
+        self.player.draw(ctx, Some(mesh_builder))?;
+        let mut fading = vec![];
+        for (enemy, life) in self.enemies.actors().iter().zip(self.enemies.lives()) {
+            let boundary = self.arena.boundary_of(enemy.as_ref());
+            if life.is_fading() {
+                fading.push((enemy, boundary == Boundary::Wrap, life.alpha()));
+                continue;
+            }
+            let mesh_builder = match boundary {
+                Boundary::Wrap => &mut wrapping,
+                _ => &mut confined,
+            };
+            let mesh_builder = mesh_builder.get_or_insert_with(graphics::MeshBuilder::new);
+            enemy.draw(ctx, Some(mesh_builder))?;
+        }
+        if let Some(mesh_builder) = wrapping {
+            let mesh = mesh_builder.build(ctx)?;
+            self.draw_mesh(ctx, &mesh, true, graphics::WHITE)?;
+        }
+        if let Some(mesh_builder) = confined {
+            let mesh = mesh_builder.build(ctx)?;
+            self.draw_mesh(ctx, &mesh, false, graphics::WHITE)?;
+        }
+
+        // Each expiring enemy gets a mesh of its own, so it can be faded out
+        // by tinting it.
+        for (enemy, wraps, alpha) in fading {
+            let mesh_builder = &mut graphics::MeshBuilder::new();
+            enemy.draw(ctx, Some(mesh_builder))?;
+            let mesh = mesh_builder.build(ctx)?;
+            self.draw_mesh(ctx, &mesh, wraps, [1.0, 1.0, 1.0, alpha].into())?;
+        }
+        Ok(())
+    }
+
+    /// Draws `mesh`, and on the arena's neighbours as well if what is in it
+    /// `wraps`.
+    fn draw_mesh(
+        &self,
+        ctx: &mut ggez::Context,
+        mesh: &graphics::Mesh,
+        wraps: bool,
+        color: graphics::Color,
+    ) -> ggez::GameResult {
+        let param = graphics::DrawParam::default().color(color);
+        if !wraps {
+            return graphics::draw(ctx, mesh, param);
+        }
+        // Draw the arena's neighbours too, so anything crossing an edge shows
+        // up on the other side as well.
+        let (width, height) = (self.arena.width, self.arena.height);
+        for &y in [-height, 0.0, height].iter() {
+            for &x in [-width, 0.0, width].iter() {
+                graphics::draw(ctx, mesh, param.dest(Point2::new(x, y)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn new_game(seed: u64) -> Simulation {
+        Simulation::new(
+            seed,
+            Arena::default(),
+            Arc::new(Archetypes::default()),
+            Spawner::endless(Difficulty::default()),
+        )
+    }
+
+    /// Wanders around, so that the player doesn't just sit in the middle.
+    fn input(tick: u32) -> Input {
+        Input::from_bits(((tick / 20) % 16) as u8).unwrap()
+    }
+
+    #[test]
+    fn moves_the_player_from_input() {
+        let mut game = new_game(1);
+        let start = game.player.get_pos();
+        let right = Input { right: true, ..Input::default() };
+        for _ in 0..60 {
+            game.tick(DT, right);
+        }
+        let moved = game.player.get_pos() - start;
+        assert!((moved.x - 150.0).abs() < 1.0, "moved {:?}", moved);
+        assert_eq!(moved.y, 0.0);
+    }
+
+    #[test]
+    fn spawns_an_enemy_every_interval() {
+        let mut game = new_game(1);
+        for _ in 0..60 * 6 {
+            game.tick(DT, Input::default());
+        }
+        assert_eq!(game.enemies.actors().len(), 1);
+        for _ in 0..60 * 5 {
+            game.tick(DT, Input::default());
+        }
+        assert_eq!(game.enemies.actors().len(), 2);
+    }
+
+    #[test]
+    fn same_seed_and_inputs_play_out_the_same() {
+        let (mut a, mut b) = (new_game(7), new_game(7));
+        for tick in 0..60 * 20 {
+            a.tick(DT, input(tick));
+            b.tick(DT, input(tick));
+            assert_eq!(a.checksum(), b.checksum(), "desync at tick {}", tick);
+        }
+    }
+
+    #[test]
+    fn mine_blasts_set_off_mines_in_reach() {
+        let mut game = new_game(5);
+        let mut mine_at = |dx: f32| {
+            let mine = Mine::new(
+                game.player.get_pos() + Vector2::new(dx, 0.0),
+                Vector2::new(10.0, 10.0),
+                graphics::WHITE,
+                0.0,
+                80.0,
+                40.0,
+                0.5,
+            );
+            game.enemies.insert(Spawn {
+                actor: Box::new(mine),
+                expiry: Default::default(),
+                tags: vec![],
+            })
+        };
+        // The first is set off by the player without the blast reaching them,
+        // and reaches only the nearer of the others.
+        mine_at(70.0);
+        let (near, far) = (mine_at(110.0), mine_at(200.0));
+        for _ in 0..40 {
+            assert!(game.tick(DT, Input::default()).is_empty());
+        }
+        let is_lethal = |id| game.enemies.get(id).unwrap().is_lethal();
+        assert!(is_lethal(near));
+        assert!(!is_lethal(far));
+    }
+
+    #[test]
+    fn snapshot_keeps_the_checksum() {
+        let mut game = new_game(11);
+        for tick in 0..60 * 10 {
+            game.tick(DT, input(tick));
+        }
+        let mut loaded = Simulation::load_snapshot(&game.save_snapshot()).unwrap();
+        assert_eq!(game.checksum(), loaded.checksum());
+        for tick in 0..60 * 10 {
+            game.tick(DT, input(tick));
+            loaded.tick(DT, input(tick));
+        }
+        assert_eq!(game.checksum(), loaded.checksum());
+    }
+
+    #[test]
+    fn snapshot_rejects_splitters_that_split_forever() {
+        let mut game = new_game(17);
+        let fragments: Fragments = ron::de::from_str(&format!(
+            "(split: After(1.0), count: 1, spread: 0.0, depth: {})",
+            u32::MAX,
+        )).unwrap();
+        let bullet = game.archetypes.get("bullet").unwrap().clone();
+        let splitter = SplitterBullet::new(
+            game.player.get_pos() + Vector2::new(100.0, 0.0),
+            Vector2::new(10.0, 10.0),
+            Vector2::new(0.0, 0.0),
+            graphics::WHITE,
+            fragments,
+            bullet,
+        );
+        game.enemies.insert(Spawn {
+            actor: Box::new(splitter),
+            expiry: Default::default(),
+            tags: vec![],
+        });
+        assert!(Simulation::load_snapshot(&game.save_snapshot()).is_err());
+    }
+
+    #[test]
+    fn restoring_progress_rewinds() {
+        let mut game = new_game(13);
+        for tick in 0..60 * 5 {
+            game.tick(DT, input(tick));
+        }
+        let (progress, checksum) = (game.save_progress(), game.checksum());
+        for tick in 0..60 {
+            game.tick(DT, input(tick));
+        }
+        game.restore_progress(&progress).unwrap();
+        assert_eq!(game.checksum(), checksum);
+    }
+}
@@ -0,0 +1,204 @@
+use ggez::graphics::Rect;
+use ggez::nalgebra::{Point2, Vector2};
+
+use crate::actors::Actor;
+use crate::arena::{Arena, Boundary};
+use crate::collision::{swept_bounds, Shape};
+
+/// Uniform grid over the arena for finding actors near a point or rect
+/// without testing every one of them.
+///
+/// If the arena wraps, anything crossing an edge is also found from the
+/// opposite side, and queries match actors that wrap the way they overlap
+/// across the edges.
+///
+/// Queries return indices into the slice the grid was built from, in
+/// ascending order.
+pub struct SpatialGrid {
+    arena: Arena,
+    cell_size: f32,
+    cols: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>,
+    shapes: Vec<Shape>,
+    boundaries: Vec<Boundary>,
+}
+
+impl SpatialGrid {
+    pub fn new(arena: Arena, cell_size: f32) -> Self {
+        let cols = (arena.width / cell_size).ceil().max(1.0) as usize;
+        let rows = (arena.height / cell_size).ceil().max(1.0) as usize;
+        Self {
+            arena,
+            cell_size,
+            cols,
+            rows,
+            cells: vec![vec![]; cols * rows],
+            shapes: vec![],
+            boundaries: vec![],
+        }
+    }
+
+    /// Re-buckets `actors`, covering everywhere they passed through since
+    /// they moved by `motions`.
+    pub fn rebuild(&mut self, actors: &[Box<dyn Actor>], motions: &[Vector2<f32>]) {
+        for cell in self.cells.iter_mut() {
+            cell.clear();
+        }
+        self.shapes.clear();
+        self.boundaries.clear();
+        for (i, (actor, &motion)) in actors.iter().zip(motions).enumerate() {
+            let shape = actor.shape();
+            for cell in self.cells_covering(swept_bounds(shape.bounds(), motion)) {
+                self.cells[cell].push(i);
+            }
+            self.shapes.push(shape);
+            self.boundaries.push(self.arena.boundary_of(actor.as_ref()));
+        }
+    }
+
+    /// Actors whose shape overlaps `rect`.
+    pub fn query_rect(&self, rect: Rect) -> Vec<usize> {
+        self.query(&Shape::Aabb(rect))
+    }
+
+    /// Actors whose shape comes within `radius` of `center`.
+    pub fn query_radius(&self, center: Point2<f32>, radius: f32) -> Vec<usize> {
+        self.query(&Shape::Circle { center, radius })
+    }
+
+    fn query(&self, area: &Shape) -> Vec<usize> {
+        let bounds = area.bounds();
+        let mut found = self.candidates(bounds);
+        found.retain(|&i| {
+            let shape = &self.shapes[i];
+            self.arena.offsets(self.boundaries[i], bounds, shape.bounds())
+                .into_iter()
+                .any(|offset| area.intersects(&shape.translate(offset)))
+        });
+        found
+    }
+
+    /// Actors that may have passed through `rect` since the last rebuild,
+    /// for narrower tests to filter.
+    pub fn candidates(&self, rect: Rect) -> Vec<usize> {
+        let mut found = vec![];
+        for cell in self.cells_covering(rect) {
+            found.extend_from_slice(&self.cells[cell]);
+        }
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    /// Cells covered by `rect`. Whatever sticks out past an edge is wrapped
+    /// around to the other side if the arena wraps, or clamped otherwise.
+    fn cells_covering(&self, rect: Rect) -> Vec<usize> {
+        let range = |start: f32, len: f32, size: f32, count: usize| {
+            if self.arena.wraps() {
+                wrapped_range(start, len, size, self.cell_size, count)
+            }
+            else {
+                clamped_range(start, len, self.cell_size, count)
+            }
+        };
+        let cols = range(rect.left(), rect.w, self.arena.width, self.cols);
+        let rows = range(rect.top(), rect.h, self.arena.height, self.rows);
+        let mut cells = Vec::with_capacity(cols.len() * rows.len());
+        for &row in rows.iter() {
+            for &col in cols.iter() {
+                cells.push(row * self.cols + col);
+            }
+        }
+        cells
+    }
+}
+
+fn cell_index(x: f32, cell_size: f32, count: usize) -> usize {
+    ((x / cell_size).floor().max(0.0) as usize).min(count - 1)
+}
+
+/// Indices of the `count` cells of `cell_size` covering `start` to
+/// `start + len`, clamped to the ends of the axis.
+fn clamped_range(start: f32, len: f32, cell_size: f32, count: usize) -> Vec<usize> {
+    let first = cell_index(start, cell_size, count);
+    let last = cell_index(start + len, cell_size, count);
+    (first..=last).collect()
+}
+
+/// Like `clamped_range`, along an axis that wraps around at `size`.
+fn wrapped_range(
+    start: f32,
+    len: f32,
+    size: f32,
+    cell_size: f32,
+    count: usize,
+) -> Vec<usize> {
+    if len >= size {
+        return (0..count).collect();
+    }
+    let index = |x: f32| cell_index(x, cell_size, count);
+    let start = start.rem_euclid(size);
+    let end = start + len;
+    if end < size {
+        (index(start)..=index(end)).collect()
+    }
+    else {
+        (index(start)..count).chain(0..=index(end - size)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actors::Bullet;
+    use ggez::graphics::WHITE;
+
+    /// A grid of 64px cells holding 10px bullets centred on `positions`.
+    fn grid_with(arena: Arena, positions: &[(f32, f32)]) -> SpatialGrid {
+        let mut bullets: Vec<Box<dyn Actor>> = vec![];
+        for &(x, y) in positions {
+            bullets.push(Box::new(Bullet::new(
+                Point2::new(x, y),
+                Vector2::new(10.0, 10.0),
+                Vector2::new(0.0, 0.0),
+                WHITE,
+            )));
+        }
+        let motions = vec![Vector2::new(0.0, 0.0); bullets.len()];
+        let mut grid = SpatialGrid::new(arena, 64.0);
+        grid.rebuild(&bullets, &motions);
+        grid
+    }
+
+    #[test]
+    fn rect_query_only_finds_overlapping_shapes() {
+        let grid = grid_with(Arena::default(), &[(100.0, 100.0), (120.0, 120.0)]);
+        assert_eq!(grid.candidates(Rect::new(106.0, 106.0, 3.0, 3.0)), vec![0, 1]);
+        assert_eq!(grid.query_rect(Rect::new(106.0, 106.0, 3.0, 3.0)), Vec::<usize>::new());
+        assert_eq!(grid.query_rect(Rect::new(104.0, 104.0, 3.0, 3.0)), vec![0]);
+        assert_eq!(grid.query_rect(Rect::new(90.0, 90.0, 40.0, 40.0)), vec![0, 1]);
+    }
+
+    #[test]
+    fn radius_query_only_finds_shapes_in_reach() {
+        let grid = grid_with(Arena::default(), &[(100.0, 100.0), (120.0, 120.0)]);
+        assert_eq!(grid.query_radius(Point2::new(110.0, 100.0), 4.0), Vec::<usize>::new());
+        assert_eq!(grid.query_radius(Point2::new(110.0, 100.0), 6.0), vec![0]);
+        // Both nearest corners are 7.07px away.
+        assert_eq!(grid.query_radius(Point2::new(110.0, 110.0), 7.0), Vec::<usize>::new());
+        assert_eq!(grid.query_radius(Point2::new(110.0, 110.0), 7.5), vec![0, 1]);
+    }
+
+    #[test]
+    fn queries_look_across_edges_that_wrap() {
+        let positions = [(798.0, 300.0)];
+        let wrapping = grid_with(Arena::default(), &positions);
+        assert_eq!(wrapping.query_rect(Rect::new(0.0, 295.0, 5.0, 10.0)), vec![0]);
+        assert_eq!(wrapping.query_radius(Point2::new(5.0, 300.0), 3.0), vec![0]);
+
+        let open = grid_with(Arena::MODES[2].1, &positions);
+        assert_eq!(open.query_rect(Rect::new(0.0, 295.0, 5.0, 10.0)), Vec::<usize>::new());
+        assert_eq!(open.query_radius(Point2::new(5.0, 300.0), 3.0), Vec::<usize>::new());
+    }
+}
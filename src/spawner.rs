@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use ggez::nalgebra::Point2;
+use rand::Rng;
+
+use crate::actors::{ActorSet, Spawn};
+use crate::archetype::Archetypes;
+use crate::arena::Arena;
+use crate::difficulty::Difficulty;
+use crate::level::{Formation, Goal, Level, LevelRun, Wave};
+use crate::rng::GameRng;
+use crate::state::{self, StateReader, StateWriter};
+
+/// Decides when enemies come in, and when the game is won.
+#[derive(Clone)]
+pub enum Spawner {
+    /// Sends in random enemies forever, more and more often as the
+    /// `difficulty` ramps up.
+    Endless {
+        /// Seconds until the next enemy.
+        timer: f32,
+        difficulty: Difficulty,
+    },
+    /// Plays out an authored level.
+    Level(LevelRun),
+}
+
+impl Spawner {
+    pub fn endless(difficulty: Difficulty) -> Self {
+        Spawner::Endless {
+            timer: difficulty.interval(0.0),
+            difficulty,
+        }
+    }
+
+    pub fn level(level: Arc<Level>) -> Self {
+        Spawner::Level(LevelRun::new(level))
+    }
+
+    /// Name of the level being played, if any.
+    pub fn level_name(&self) -> Option<&str> {
+        match self {
+            Spawner::Endless { .. } => None,
+            Spawner::Level(run) => Some(run.level().name()),
+        }
+    }
+
+    /// Difficulty curve of endless mode, if that is what is being played.
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        match self {
+            Spawner::Endless { difficulty, .. } => Some(*difficulty),
+            Spawner::Level(_) => None,
+        }
+    }
+
+    /// Advances by `dt` seconds, to `total_time` seconds into the game, and
+    /// returns the enemies that come in.
+    pub fn tick(
+        &mut self,
+        dt: f32,
+        total_time: f32,
+        rng: &mut GameRng,
+        archetypes: &Archetypes,
+        arena: &Arena,
+        target: Point2<f32>,
+    ) -> Vec<Spawn> {
+        match self {
+            Spawner::Endless { timer, difficulty } => {
+                *timer -= dt;
+                if *timer > 0.0 {
+                    return vec![];
+                }
+                *timer += difficulty.interval(total_time);
+                difficulty.spawn(total_time, rng, archetypes, arena, target)
+                    .into_iter()
+                    .collect()
+            }
+            Spawner::Level(run) => {
+                let mut enemies = vec![];
+                for wave in run.advance(dt, rng) {
+                    spawn_wave(&wave, rng, archetypes, arena, target, &mut enemies);
+                }
+                enemies
+            }
+        }
+    }
+
+    /// Whether the player has won, `total_time` seconds into the game.
+    pub fn is_cleared(&self, total_time: f32, enemies: &ActorSet) -> bool {
+        match self {
+            Spawner::Endless { .. } => false,
+            Spawner::Level(run) => match run.level().goal() {
+                Goal::Survive(seconds) => total_time >= seconds,
+                Goal::Clear => {
+                    run.is_finished()
+                        && enemies.with_tag("hazard").all(|(id, _)| {
+                            enemies.life(id).is_some_and(|life| life.is_fading())
+                        })
+                }
+            },
+        }
+    }
+
+    /// Makes sure every archetype it may spawn exists.
+    pub fn check(&self, archetypes: &Archetypes) -> Result<(), String> {
+        match self {
+            Spawner::Endless { .. } => Ok(()),
+            Spawner::Level(run) => run.level().check(archetypes),
+        }
+    }
+
+    pub fn write_state(&self, w: &mut StateWriter) {
+        match self {
+            Spawner::Endless { timer, difficulty } => {
+                w.write_u8(0);
+                w.write_f32(*timer);
+                difficulty.write_state(w);
+            }
+            Spawner::Level(run) => {
+                w.write_u8(1);
+                run.write_state(w);
+            }
+        }
+    }
+
+    /// Like `write_state`, leaving out the level, which never changes during
+    /// a game.
+    pub fn write_progress(&self, w: &mut StateWriter) {
+        match self {
+            Spawner::Endless { .. } => self.write_state(w),
+            Spawner::Level(run) => {
+                w.write_u8(1);
+                run.write_progress(w);
+            }
+        }
+    }
+
+    /// Reads what `write_progress` wrote for the same game as `self`.
+    pub fn read_progress(&self, r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(match (r.read_u8()?, self) {
+            (0, Spawner::Endless { .. }) => Spawner::Endless {
+                timer: r.read_f32()?,
+                difficulty: Difficulty::read_state(r)?,
+            },
+            (1, Spawner::Level(run)) => Spawner::Level(run.read_progress(r)?),
+            (v, _) => return Err(state::invalid(format!("invalid spawner {}", v))),
+        })
+    }
+
+    pub fn read_state(r: &mut StateReader) -> ggez::GameResult<Self> {
+        Ok(match r.read_u8()? {
+            0 => Spawner::Endless {
+                timer: r.read_f32()?,
+                difficulty: Difficulty::read_state(r)?,
+            },
+            1 => Spawner::Level(LevelRun::read_state(r)?),
+            v => return Err(state::invalid(format!("invalid spawner {}", v))),
+        })
+    }
+}
+
+fn spawn_wave(
+    wave: &Wave,
+    rng: &mut GameRng,
+    archetypes: &Archetypes,
+    arena: &Arena,
+    target: Point2<f32>,
+    enemies: &mut Vec<Spawn>,
+) {
+    let archetype = match archetypes.get(&wave.archetype) {
+        Some(archetype) => archetype,
+        None => return,
+    };
+    let edge = wave.edge.pick(rng);
+    let cluster = rng.gen_range(0.0, 1.0);
+    for i in 0..wave.count {
+        let along = match wave.formation {
+            Formation::Scattered => rng.gen_range(0.0, 1.0),
+            Formation::Line => (i as f32 + 0.5) / wave.count as f32,
+            Formation::Cluster => cluster,
+        };
+        enemies.extend(archetypes.build(archetype, rng, arena, edge, along, target));
+    }
+}
@@ -0,0 +1,172 @@
+use ggez::{GameError, GameResult};
+use ggez::graphics::Color;
+use ggez::nalgebra::{Point2, Vector2};
+
+/// Flattens gameplay state into bytes, field by field.
+///
+/// Floats are written by their bit pattern, so two states only produce the
+/// same bytes if they are exactly equal.
+#[derive(Default)]
+pub struct StateWriter {
+    bytes: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_u8(&mut self, v: u8) {
+        self.bytes.push(v);
+    }
+
+    pub fn write_u32(&mut self, v: u32) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, v: u64) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, v: f32) {
+        self.write_u32(v.to_bits());
+    }
+
+    pub fn write_point(&mut self, p: Point2<f32>) {
+        self.write_f32(p.x);
+        self.write_f32(p.y);
+    }
+
+    pub fn write_vector(&mut self, v: Vector2<f32>) {
+        self.write_f32(v.x);
+        self.write_f32(v.y);
+    }
+
+    pub fn write_color(&mut self, c: Color) {
+        self.write_f32(c.r);
+        self.write_f32(c.g);
+        self.write_f32(c.b);
+        self.write_f32(c.a);
+    }
+
+    pub fn write_str(&mut self, s: &str) {
+        self.write_u32(s.len() as u32);
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    pub fn write_strs(&mut self, strs: &[String]) {
+        self.write_u32(strs.len() as u32);
+        for s in strs {
+            self.write_str(s);
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// 64-bit FNV-1a hash of everything written so far.
+    pub fn checksum(&self) -> u64 {
+        self.bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+}
+
+/// Reads back what a `StateWriter` wrote, in the same order.
+pub struct StateReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take(&mut self, len: usize) -> GameResult<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(invalid("unexpected end of data".to_owned()));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub fn read_u8(&mut self) -> GameResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn read_u32(&mut self) -> GameResult<u32> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    pub fn read_u64(&mut self) -> GameResult<u64> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    pub fn read_f32(&mut self) -> GameResult<f32> {
+        Ok(f32::from_bits(self.read_u32()?))
+    }
+
+    pub fn read_point(&mut self) -> GameResult<Point2<f32>> {
+        Ok(Point2::new(self.read_f32()?, self.read_f32()?))
+    }
+
+    pub fn read_vector(&mut self) -> GameResult<Vector2<f32>> {
+        Ok(Vector2::new(self.read_f32()?, self.read_f32()?))
+    }
+
+    /// Like `read_point`, but rejects NaN and infinite coordinates.
+    pub fn read_finite_point(&mut self) -> GameResult<Point2<f32>> {
+        let p = self.read_point()?;
+        if !p.x.is_finite() || !p.y.is_finite() {
+            return Err(invalid(format!("non-finite point ({}, {})", p.x, p.y)));
+        }
+        Ok(p)
+    }
+
+    /// Like `read_vector`, but rejects NaN and infinite components.
+    pub fn read_finite_vector(&mut self) -> GameResult<Vector2<f32>> {
+        let v = self.read_vector()?;
+        if !v.x.is_finite() || !v.y.is_finite() {
+            return Err(invalid(format!("non-finite vector ({}, {})", v.x, v.y)));
+        }
+        Ok(v)
+    }
+
+    pub fn read_color(&mut self) -> GameResult<Color> {
+        Ok(Color::new(
+            self.read_f32()?,
+            self.read_f32()?,
+            self.read_f32()?,
+            self.read_f32()?,
+        ))
+    }
+
+    pub fn read_str(&mut self) -> GameResult<&'a str> {
+        let len = self.read_u32()? as usize;
+        std::str::from_utf8(self.take(len)?)
+            .map_err(|_| invalid("string is not valid UTF-8".to_owned()))
+    }
+
+    pub fn read_strs(&mut self) -> GameResult<Vec<String>> {
+        let count = self.read_u32()?;
+        let mut strs = vec![];
+        for _ in 0..count {
+            strs.push(self.read_str()?.to_owned());
+        }
+        Ok(strs)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+pub fn invalid(reason: String) -> GameError {
+    GameError::ResourceLoadError(format!("Invalid state: {}", reason))
+}